@@ -12,34 +12,69 @@ use core::fmt;
  */
 use std::{
     borrow::Cow,
+    cell::Cell,
+    collections::HashMap,
     fmt::{Display, Formatter},
-    net::{Ipv4Addr, Ipv6Addr},
+    future::Future,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     str::FromStr,
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-use hickory_client::proto::rr::dnssec::{KeyPair, Private};
+use futures_util::{
+    future::{self, BoxFuture},
+    stream, FutureExt, Stream, TryStreamExt,
+};
+use hickory_client::proto::{
+    op::ResponseCode,
+    rr::{
+        dnssec::{KeyPair, Private},
+        DNSClass,
+    },
+};
+use http::{HttpClientBuilder, PlannedRequest, RateLimitInfo};
 use providers::{
+    base64_decode,
     cloudflare::CloudflareProvider,
+    dreamhost::DreamhostProvider,
+    duckdns::DuckDnsProvider,
+    infomaniak::InfomaniakProvider,
+    njalla::NjallaProvider,
     rfc2136::{DnsAddress, Rfc2136Provider},
 };
+use tokio::sync::OnceCell;
 
 pub mod http;
 pub mod providers;
+pub mod resolver;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod url;
 
 #[derive(Debug)]
 pub enum Error {
     Protocol(String),
     Parse(String),
+    /// The updater was built with invalid or missing settings, such as an
+    /// empty credential or an unparseable server address.
+    Config(String),
     Client(String),
     Response(String),
     Api(String),
     Serialize(String),
     Unauthorized,
     NotFound,
+    ZoneNotFound(String),
+    RecordNotFound(String),
+    Cancelled,
+    /// An operation did not complete in time, such as a record that did not
+    /// propagate.
+    Timeout(String),
 }
 
 /// A DNS record type.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DnsRecord {
     A {
         content: Ipv4Addr,
@@ -66,6 +101,99 @@ pub enum DnsRecord {
         weight: u16,
         port: u16,
     },
+    CAA {
+        flags: u8,
+        tag: String,
+        value: String,
+    },
+    /// A certificate (RFC 4398), `content` is base64 encoded.
+    CERT {
+        cert_type: u16,
+        key_tag: u16,
+        algorithm: u8,
+        content: String,
+    },
+    /// An S/MIME certificate association (RFC 8162), `data` is hex encoded.
+    SMIMEA {
+        usage: u8,
+        selector: u8,
+        matching_type: u8,
+        data: String,
+    },
+    /// An OpenPGP public key (RFC 7929), `content` is base64 encoded.
+    OPENPGPKEY {
+        content: String,
+    },
+    /// A legacy SPF record (type 99, RFC 4408), quoted and split into
+    /// character-strings like a TXT record. Most providers only accept SPF
+    /// policies as TXT records.
+    SPF {
+        content: String,
+    },
+}
+
+/// The type of a DNS record, without its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum DnsRecordType {
+    A,
+    AAAA,
+    CNAME,
+    NS,
+    MX,
+    TXT,
+    SRV,
+    CAA,
+    CERT,
+    SMIMEA,
+    OPENPGPKEY,
+    SPF,
+}
+
+/// What a provider supports, as reported by [`DnsUpdater::capabilities`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Record types that can be created, updated and read.
+    pub record_types: Cow<'static, [DnsRecordType]>,
+    /// Whether [`DnsUpdater::list`] and the operations built on it work.
+    pub supports_list: bool,
+    /// Whether several records of the same type can coexist at a name.
+    pub supports_multi_value: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, record_type: DnsRecordType) -> bool {
+        self.record_types.contains(&record_type)
+    }
+}
+
+/// A record exported from a zone, see [`DnsUpdater::export_zone`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedRecord {
+    pub name: String,
+    pub record: DnsRecord,
+    pub ttl: u32,
+}
+
+/// The CAA policy of a zone, as reported by [`DnsUpdater::ensure_caa_allows`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaaStatus {
+    /// The CAA records published at the zone apex.
+    pub records: Vec<DnsRecord>,
+    /// Whether the CA is allowed to issue certificates for the zone.
+    pub allowed: bool,
+    /// Whether a CAA record was created because none existed.
+    pub created: bool,
+}
+
+/// The delegation signer (DS) data of a DNSSEC-signed zone, to be
+/// published at the registrar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnssecKey {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: String,
 }
 
 /// A TSIG algorithm.
@@ -93,10 +221,153 @@ pub enum Algorithm {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+const NOT_FOUND_RETRIES: u32 = 3;
+const NOT_FOUND_BACKOFF: Duration = Duration::from_millis(500);
+
+/// A logical operation reported to a [`MetricsCallback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+}
+
+/// Timing and outcome of an operation, see [`DnsUpdater::with_metrics`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationMetric {
+    pub provider: &'static str,
+    pub operation: Operation,
+    pub duration: Duration,
+    pub success: bool,
+}
+
+/// Callback receiving an [`OperationMetric`] after each operation.
+pub type MetricsCallback = Arc<dyn Fn(OperationMetric) + Send + Sync>;
+
+/// Provider specific options for [`DnsUpdater::create_with_options`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CreateOptions {
+    /// Proxy the record through Cloudflare (A, AAAA and CNAME only).
+    pub proxied: bool,
+    /// Cloudflare record tags, e.g. `owner:acme`.
+    pub tags: Vec<String>,
+    /// Provider specific fields merged as is into the JSON body of the request,
+    /// e.g. `{"comment": "..."}` on Cloudflare. They are not validated. Only
+    /// used by Cloudflare and Infomaniak.
+    pub extra: Option<serde_json::Value>,
+}
+
+/// SOA timers to change with [`DnsUpdater::set_zone_soa`]. Fields left as
+/// `None` keep their current value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SoaParams {
+    pub refresh: Option<u32>,
+    pub retry: Option<u32>,
+    pub expire: Option<u32>,
+    pub minimum: Option<u32>,
+}
+
+/// A single record change, see [`DnsUpdater::describe_request`]. Names are
+/// fully qualified, as passed to [`DnsUpdater::create`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Create {
+        name: String,
+        record: DnsRecord,
+        ttl: u32,
+    },
+    Update {
+        name: String,
+        record: DnsRecord,
+        ttl: u32,
+    },
+    Delete {
+        name: String,
+    },
+}
+
+/// A set of record changes applied together by [`DnsUpdater::apply_batch`].
+/// Records are given as `(name, record, ttl)`, deleted records by name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Batch {
+    pub creates: Vec<(String, DnsRecord, Ttl)>,
+    pub updates: Vec<(String, DnsRecord, Ttl)>,
+    pub deletes: Vec<String>,
+}
+
+/// A change computed by [`DnsUpdater::plan`] and applied by
+/// [`DnsUpdater::apply`]. Names are fully qualified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedChange {
+    Create {
+        name: String,
+        record: DnsRecord,
+        ttl: Ttl,
+    },
+    /// Replace the only record of this type at `name`.
+    Update {
+        name: String,
+        record: DnsRecord,
+        ttl: Ttl,
+    },
+    /// Delete the record at `name` matching `record`.
+    Delete { name: String, record: DnsRecord },
+}
+
+/// The TTL of a record created or updated through [`DnsUpdater`]. A plain
+/// `u32` converts to [`Ttl::Seconds`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ttl {
+    Seconds(u32),
+    /// Let the provider choose (Cloudflare's "automatic" TTL).
+    Auto,
+    /// The zone's default TTL, where the provider has one.
+    ZoneDefault,
+}
+
+/// The target name of a CNAME, MX, NS or SRV record. A target containing a
+/// dot is [`Target::Absolute`] even without a trailing dot, so
+/// `mail.other-domain.com` is never read relative to the zone being updated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// A fully qualified name, stored without the trailing dot.
+    Absolute(String),
+    /// A single label relative to the zone origin.
+    Relative(String),
+}
+
+/// TTL used for [`Ttl::Auto`] and [`Ttl::ZoneDefault`] by providers that
+/// require an explicit value.
+pub const DEFAULT_TTL: u32 = 3600;
+
+/// Endpoint used by [`DnsUpdater::update_to_public_ip`] to discover the
+/// caller's public IPv4 address.
+pub const DEFAULT_PUBLIC_IP_ENDPOINT: &str = "https://api.ipify.org";
+
 #[derive(Clone)]
 pub enum DnsUpdater {
     Rfc2136(Rfc2136Provider),
     Cloudflare(CloudflareProvider),
+    Infomaniak(InfomaniakProvider),
+    Njalla(NjallaProvider),
+    Dreamhost(DreamhostProvider),
+    DuckDns(DuckDnsProvider),
+    /// Several updaters kept in sync, see [`DnsUpdater::new_multi`].
+    Multi(Vec<DnsUpdater>),
+}
+
+/// A DNS updater bound to a single zone, see [`DnsUpdater::zone`].
+#[derive(Clone)]
+pub struct ZoneUpdater {
+    updater: DnsUpdater,
+    origin: String,
+}
+
+/// A shared cache of values obtained from a provider's API, such as record IDs.
+/// Concurrent lookups of the same key share a single in-flight fetch.
+#[derive(Clone)]
+pub struct ApiCacheManager<T> {
+    entries: Arc<Mutex<HashMap<String, Arc<OnceCell<T>>>>>,
 }
 
 pub trait IntoFqdn<'x> {
@@ -106,6 +377,8 @@ pub trait IntoFqdn<'x> {
 
 impl DnsUpdater {
     /// Create a new DNS updater using the RFC 2136 protocol and TSIG authentication.
+    /// `addr` is either a [`DnsAddress`], such as `DnsAddress::Tcp(socket_addr)`,
+    /// or a string such as `udp://127.0.0.1:53`.
     pub fn new_rfc2136_tsig(
         addr: impl TryInto<DnsAddress>,
         key_name: impl AsRef<str>,
@@ -120,7 +393,35 @@ impl DnsUpdater {
         )?))
     }
 
+    /// Same as [`DnsUpdater::new_rfc2136_tsig`], taking the TSIG secret in the
+    /// base64 form found in `named.conf` or produced by `tsig-keygen`.
+    pub fn new_rfc2136_tsig_base64(
+        addr: impl TryInto<DnsAddress>,
+        key_name: impl AsRef<str>,
+        key: &str,
+        algorithm: TsigAlgorithm,
+    ) -> crate::Result<Self> {
+        Self::new_rfc2136_tsig(addr, key_name, base64_decode(key.trim())?, algorithm)
+    }
+
+    /// Same as [`DnsUpdater::new_rfc2136_tsig`], sending updates from a specific
+    /// local address. Useful on multi-homed hosts when the DNS server only
+    /// accepts updates from some source addresses.
+    pub fn new_rfc2136_tsig_with_bind(
+        addr: impl TryInto<DnsAddress>,
+        key_name: impl AsRef<str>,
+        key: impl Into<Vec<u8>>,
+        algorithm: TsigAlgorithm,
+        bind_addr: SocketAddr,
+    ) -> crate::Result<Self> {
+        Ok(DnsUpdater::Rfc2136(
+            Rfc2136Provider::new_tsig(addr, key_name, key, algorithm.into())?
+                .with_bind_addr(Some(bind_addr)),
+        ))
+    }
+
     /// Create a new DNS updater using the RFC 2136 protocol and SIG(0) authentication.
+    /// `addr` accepts the same forms as in [`DnsUpdater::new_rfc2136_tsig`].
     pub fn new_rfc2136_sig0(
         addr: impl TryInto<DnsAddress>,
         signer_name: impl AsRef<str>,
@@ -148,17 +449,432 @@ impl DnsUpdater {
         )?))
     }
 
-    /// Create a new DNS record.
+    /// Create a new DNS updater using the Infomaniak API.
+    pub fn new_infomaniak(
+        secret: impl AsRef<str>,
+        timeout: Option<Duration>,
+    ) -> crate::Result<Self> {
+        Ok(DnsUpdater::Infomaniak(InfomaniakProvider::new(
+            secret, timeout,
+        )?))
+    }
+
+    /// Create a new DNS updater using the Njalla API.
+    pub fn new_njalla(
+        api_token: impl AsRef<str>,
+        timeout: Option<Duration>,
+    ) -> crate::Result<Self> {
+        Ok(DnsUpdater::Njalla(NjallaProvider::new(api_token, timeout)?))
+    }
+
+    /// Create a new DNS updater using the DreamHost API. DreamHost does not
+    /// support per-record TTLs, so the `ttl` of created records is ignored.
+    pub fn new_dreamhost(
+        api_key: impl AsRef<str>,
+        timeout: Option<Duration>,
+    ) -> crate::Result<Self> {
+        Ok(DnsUpdater::Dreamhost(DreamhostProvider::new(
+            api_key, timeout,
+        )?))
+    }
+
+    /// Create a new DNS updater using DuckDNS. Only the A, AAAA and TXT
+    /// records of `<domain>.duckdns.org` can be changed, and TTLs are ignored.
+    pub fn new_duckdns(token: impl AsRef<str>, timeout: Option<Duration>) -> crate::Result<Self> {
+        Ok(DnsUpdater::DuckDns(DuckDnsProvider::new(token, timeout)?))
+    }
+
+    /// Create a DNS updater that applies every create, update and delete to
+    /// all of `updaters` concurrently, e.g. to keep a secondary provider in
+    /// sync. An operation succeeds only if it succeeds on every updater,
+    /// otherwise the failures are reported together in [`Error::Api`]. Reads
+    /// such as [`DnsUpdater::get_all`] and [`DnsUpdater::list`] are served by
+    /// the first updater.
+    pub fn new_multi(updaters: Vec<DnsUpdater>) -> crate::Result<Self> {
+        if updaters.is_empty() {
+            Err(Error::Config("no updaters given".to_string()))
+        } else {
+            Ok(DnsUpdater::Multi(updaters))
+        }
+    }
+
+    /// Use a DNS class other than `IN` for RFC 2136 queries and updates, e.g.
+    /// `CH` for `version.bind`. Has no effect on HTTP providers.
+    pub fn with_dns_class(self, dns_class: DNSClass) -> Self {
+        match self {
+            DnsUpdater::Rfc2136(provider) => {
+                DnsUpdater::Rfc2136(provider.with_dns_class(dns_class))
+            }
+            DnsUpdater::Multi(updaters) => DnsUpdater::Multi(
+                updaters
+                    .into_iter()
+                    .map(|updater| updater.with_dns_class(dns_class))
+                    .collect(),
+            ),
+            updater => updater,
+        }
+    }
+
+    /// Raise TTLs below the provider's minimum to that minimum, logging a
+    /// warning, instead of letting the provider reject the request. Only
+    /// Cloudflare (60 seconds) has a known minimum. Disabled by default.
+    pub fn with_clamp_ttl(self, clamp_ttl: bool) -> Self {
+        match self {
+            DnsUpdater::Cloudflare(provider) => {
+                DnsUpdater::Cloudflare(provider.with_clamp_ttl(clamp_ttl))
+            }
+            DnsUpdater::Multi(updaters) => DnsUpdater::Multi(
+                updaters
+                    .into_iter()
+                    .map(|updater| updater.with_clamp_ttl(clamp_ttl))
+                    .collect(),
+            ),
+            updater => updater,
+        }
+    }
+
+    /// Only look up zones owned by this Cloudflare account, for tokens that
+    /// can access zones of the same name in several accounts. Has no effect
+    /// on other providers.
+    pub fn with_account_id(self, account_id: impl Into<String>) -> Self {
+        match self {
+            DnsUpdater::Cloudflare(provider) => {
+                DnsUpdater::Cloudflare(provider.with_account_id(account_id.into()))
+            }
+            DnsUpdater::Multi(updaters) => {
+                let account_id = account_id.into();
+                DnsUpdater::Multi(
+                    updaters
+                        .into_iter()
+                        .map(|updater| updater.with_account_id(account_id.as_str()))
+                        .collect(),
+                )
+            }
+            updater => updater,
+        }
+    }
+
+    /// Limit the number of concurrent API requests issued by this updater and
+    /// its clones. Has no effect on RFC 2136 updaters.
+    pub fn with_max_concurrency(self, max_concurrency: usize) -> Self {
+        self.map_http_client(&|client| client.with_max_concurrency(max_concurrency))
+    }
+
+    /// Set how many times record creations are retried after a 429 or a 503
+    /// response. Transport errors and timeouts are not retried, as the record
+    /// may have been created. Defaults to 3, use 0 to fail fast. Has no effect
+    /// on RFC 2136 updaters.
+    pub fn with_max_retries(self, max_retries: u32) -> Self {
+        self.map_http_client(&|client| client.with_max_retries(max_retries))
+    }
+
+    /// Limit the time spent connecting to the provider's API, separately from
+    /// the overall request timeout. Has no effect on RFC 2136 updaters.
+    pub fn with_connect_timeout(self, connect_timeout: Duration) -> Self {
+        self.map_http_client(&|client| client.with_connect_timeout(connect_timeout))
+    }
+
+    /// Speak HTTP/2 to the provider's API without negotiating it first. Has no
+    /// effect on RFC 2136 updaters.
+    pub fn with_http2_prior_knowledge(self, enabled: bool) -> Self {
+        self.map_http_client(&|client| client.with_http2_prior_knowledge(enabled))
+    }
+
+    /// How long idle keep-alive connections to the provider's API are kept
+    /// open. Has no effect on RFC 2136 updaters.
+    pub fn with_pool_idle_timeout(self, timeout: Duration) -> Self {
+        self.map_http_client(&|client| client.with_pool_idle_timeout(timeout))
+    }
+
+    /// Maximum number of idle keep-alive connections kept to the provider's
+    /// API. Has no effect on RFC 2136 updaters.
+    pub fn with_pool_max_idle_per_host(self, max_idle: usize) -> Self {
+        self.map_http_client(&|client| client.with_pool_max_idle_per_host(max_idle))
+    }
+
+    /// Accept invalid TLS certificates from the provider's API, such as the
+    /// self-signed certificate of a self-hosted gateway. This disables
+    /// protection against interception and should only be used for testing.
+    /// Has no effect on RFC 2136 updaters.
+    pub fn with_danger_accept_invalid_certs(self, enabled: bool) -> Self {
+        self.map_http_client(&|client| client.with_danger_accept_invalid_certs(enabled))
+    }
+
+    /// Trust the PEM-encoded root certificates in `pem`, such as a corporate or
+    /// internal CA, when connecting to the provider's API. Has no effect on
+    /// RFC 2136 updaters.
+    pub fn with_root_certificate(self, pem: impl AsRef<[u8]>) -> crate::Result<Self> {
+        let certificates = reqwest::Certificate::from_pem_bundle(pem.as_ref())
+            .ok()
+            .filter(|certificates| !certificates.is_empty())
+            .ok_or_else(|| Error::Config("Invalid PEM root certificate".to_string()))?;
+        // The clients are built right away, a certificate that can not be used
+        // would otherwise only surface on the first request.
+        let error = Cell::new(None);
+        let updater = self.map_http_client(&|client| {
+            let client = certificates
+                .iter()
+                .cloned()
+                .fold(client, HttpClientBuilder::with_root_certificate);
+            if let Err(err) = client.validate() {
+                error.set(Some(err));
+            }
+            client
+        });
+        match error.into_inner() {
+            Some(err) => Err(err),
+            None => Ok(updater),
+        }
+    }
+
+    /// Describe the record types and operations supported by this updater.
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            DnsUpdater::Rfc2136(_) => Rfc2136Provider::CAPABILITIES,
+            DnsUpdater::Cloudflare(_) => CloudflareProvider::CAPABILITIES,
+            DnsUpdater::Infomaniak(_) => InfomaniakProvider::CAPABILITIES,
+            DnsUpdater::Dreamhost(_) => DreamhostProvider::CAPABILITIES,
+            DnsUpdater::Njalla(_) => NjallaProvider::CAPABILITIES,
+            DnsUpdater::DuckDns(_) => DuckDnsProvider::CAPABILITIES,
+            // Writes go to every updater and reads to the first one.
+            DnsUpdater::Multi(updaters) => {
+                let capabilities = updaters
+                    .iter()
+                    .map(DnsUpdater::capabilities)
+                    .collect::<Vec<_>>();
+                Capabilities {
+                    record_types: DnsRecordType::ALL
+                        .iter()
+                        .filter(|record_type| {
+                            capabilities
+                                .iter()
+                                .all(|capabilities| capabilities.supports(**record_type))
+                        })
+                        .copied()
+                        .collect(),
+                    supports_list: capabilities
+                        .first()
+                        .is_some_and(|capabilities| capabilities.supports_list),
+                    supports_multi_value: capabilities
+                        .iter()
+                        .all(|capabilities| capabilities.supports_multi_value),
+                }
+            }
+        }
+    }
+
+    /// Call `metrics` after every create, update and delete with the time the
+    /// whole operation took, including any zone or record lookups.
+    pub fn with_metrics(self, metrics: MetricsCallback) -> Self {
+        match self {
+            DnsUpdater::Rfc2136(provider) => DnsUpdater::Rfc2136(provider.with_metrics(metrics)),
+            DnsUpdater::Cloudflare(provider) => {
+                DnsUpdater::Cloudflare(provider.with_metrics(metrics))
+            }
+            DnsUpdater::Infomaniak(provider) => {
+                DnsUpdater::Infomaniak(provider.with_metrics(metrics))
+            }
+            DnsUpdater::Dreamhost(provider) => {
+                DnsUpdater::Dreamhost(provider.with_metrics(metrics))
+            }
+            DnsUpdater::Njalla(provider) => DnsUpdater::Njalla(provider.with_metrics(metrics)),
+            DnsUpdater::DuckDns(provider) => DnsUpdater::DuckDns(provider.with_metrics(metrics)),
+            DnsUpdater::Multi(updaters) => DnsUpdater::Multi(
+                updaters
+                    .into_iter()
+                    .map(|updater| updater.with_metrics(metrics.clone()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The timeout applied to each request sent to the provider.
+    pub fn timeout(&self) -> Duration {
+        match self {
+            DnsUpdater::Rfc2136(provider) => provider.timeout(),
+            DnsUpdater::Cloudflare(provider) => provider.timeout(),
+            DnsUpdater::Infomaniak(provider) => provider.timeout(),
+            DnsUpdater::Dreamhost(provider) => provider.timeout(),
+            DnsUpdater::Njalla(provider) => provider.timeout(),
+            DnsUpdater::DuckDns(provider) => provider.timeout(),
+            DnsUpdater::Multi(updaters) => updaters
+                .iter()
+                .map(DnsUpdater::timeout)
+                .max()
+                .unwrap_or(http::DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// A short name identifying the provider, e.g. `cloudflare`.
+    pub fn provider_name(&self) -> &'static str {
+        match self {
+            DnsUpdater::Rfc2136(_) => "rfc2136",
+            DnsUpdater::Cloudflare(_) => "cloudflare",
+            DnsUpdater::Infomaniak(_) => "infomaniak",
+            DnsUpdater::Dreamhost(_) => "dreamhost",
+            DnsUpdater::Njalla(_) => "njalla",
+            DnsUpdater::DuckDns(_) => "duckdns",
+            DnsUpdater::Multi(_) => "multi",
+        }
+    }
+
+    fn require_support(&self, record_type: DnsRecordType) -> crate::Result<()> {
+        if self.capabilities().supports(record_type) {
+            Ok(())
+        } else if record_type == DnsRecordType::SPF {
+            Err(Error::Api(format!(
+                "SPF records are not supported by {}, publish the policy as a TXT record instead",
+                self.provider_name()
+            )))
+        } else {
+            Err(Error::Api(format!(
+                "{record_type} records are not supported by {}",
+                self.provider_name()
+            )))
+        }
+    }
+
+    /// Interpret a TTL returned by [`DnsUpdater::get_all`] or
+    /// [`DnsUpdater::list`]. Cloudflare reports its automatic TTL as `1`,
+    /// which is returned as [`Ttl::Auto`] rather than one second.
+    pub fn ttl_from_seconds(&self, seconds: u32) -> Ttl {
+        match (seconds, self) {
+            (1, DnsUpdater::Cloudflare(_)) => Ttl::Auto,
+            // Reads are served by the first updater.
+            (seconds, DnsUpdater::Multi(updaters)) => match updaters.first() {
+                Some(updater) => updater.ttl_from_seconds(seconds),
+                None => Ttl::Seconds(seconds),
+            },
+            (seconds, _) => Ttl::Seconds(seconds),
+        }
+    }
+
+    fn ttl_seconds(&self, ttl: Ttl) -> u32 {
+        match (ttl, self) {
+            (Ttl::Seconds(seconds), _) => seconds,
+            // Cloudflare uses a TTL of 1 for "automatic", which follows the zone.
+            (Ttl::Auto | Ttl::ZoneDefault, DnsUpdater::Cloudflare(_)) => 1,
+            (Ttl::Auto | Ttl::ZoneDefault, _) => DEFAULT_TTL,
+        }
+    }
+
+    fn report<T>(&self, operation: Operation, started: Instant, result: &crate::Result<T>) {
+        let metrics = match self {
+            DnsUpdater::Rfc2136(provider) => provider.metrics(),
+            DnsUpdater::Cloudflare(provider) => provider.metrics(),
+            DnsUpdater::Infomaniak(provider) => provider.metrics(),
+            DnsUpdater::Dreamhost(provider) => provider.metrics(),
+            DnsUpdater::Njalla(provider) => provider.metrics(),
+            DnsUpdater::DuckDns(provider) => provider.metrics(),
+            DnsUpdater::Multi(_) => None,
+        };
+        if let Some(metrics) = metrics {
+            metrics(OperationMetric {
+                provider: self.provider_name(),
+                operation,
+                duration: started.elapsed(),
+                success: result.is_ok(),
+            });
+        }
+    }
+
+    /// The rate limit budget reported by the provider's API on its last
+    /// response, if any. Always `None` for RFC 2136 updaters.
+    pub fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        match self {
+            DnsUpdater::Rfc2136(_) => None,
+            DnsUpdater::Cloudflare(provider) => provider.last_rate_limit(),
+            DnsUpdater::Infomaniak(provider) => provider.last_rate_limit(),
+            DnsUpdater::Dreamhost(provider) => provider.last_rate_limit(),
+            DnsUpdater::Njalla(provider) => provider.last_rate_limit(),
+            DnsUpdater::DuckDns(provider) => provider.last_rate_limit(),
+            DnsUpdater::Multi(_) => None,
+        }
+    }
+
+    fn map_http_client(self, f: &dyn Fn(HttpClientBuilder) -> HttpClientBuilder) -> Self {
+        match self {
+            DnsUpdater::Rfc2136(_) => self,
+            DnsUpdater::Cloudflare(provider) => DnsUpdater::Cloudflare(provider.map_client(f)),
+            DnsUpdater::Infomaniak(provider) => DnsUpdater::Infomaniak(provider.map_client(f)),
+            DnsUpdater::Dreamhost(provider) => DnsUpdater::Dreamhost(provider.map_client(f)),
+            DnsUpdater::Njalla(provider) => DnsUpdater::Njalla(provider.map_client(f)),
+            DnsUpdater::DuckDns(provider) => DnsUpdater::DuckDns(provider.map_client(f)),
+            DnsUpdater::Multi(updaters) => DnsUpdater::Multi(
+                updaters
+                    .into_iter()
+                    .map(|updater| updater.map_http_client(f))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Obtain a handle to a single zone, so the origin does not need to be passed
+    /// on every call. Zone lookups are cached and shared with this updater.
+    pub fn zone<'x>(&self, origin: impl IntoFqdn<'x>) -> ZoneUpdater {
+        ZoneUpdater {
+            updater: self.clone(),
+            origin: origin.into_name().into_owned(),
+        }
+    }
+
+    /// Create a new DNS record. The record is checked with [`DnsRecord::validate`]
+    /// before being sent to the provider.
     pub async fn create(
         &self,
         name: impl IntoFqdn<'_>,
         record: DnsRecord,
-        ttl: u32,
+        ttl: impl Into<Ttl>,
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
-        match self {
+        record.validate()?;
+        self.require_support(record.as_type())?;
+        let requested_ttl = ttl.into();
+        let ttl = self.ttl_seconds(requested_ttl);
+        let started = Instant::now();
+        let result = match self {
             DnsUpdater::Rfc2136(provider) => provider.create(name, record, ttl, origin).await,
             DnsUpdater::Cloudflare(provider) => provider.create(name, record, ttl, origin).await,
+            DnsUpdater::Infomaniak(provider) => provider.create(name, record, ttl, origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.create(name, record, ttl, origin).await,
+            DnsUpdater::Njalla(provider) => provider.create(name, record, ttl, origin).await,
+            DnsUpdater::DuckDns(provider) => provider.create(name, record, ttl, origin).await,
+            DnsUpdater::Multi(updaters) => {
+                let (name, origin) = (name.into_name(), origin.into_name());
+                return fan_out(updaters, |updater| {
+                    updater.run(MultiOp::Create(
+                        name.as_ref(),
+                        record.clone(),
+                        requested_ttl,
+                        origin.as_ref(),
+                    ))
+                })
+                .await;
+            }
+        };
+        self.report(Operation::Create, started, &result);
+        result
+    }
+
+    /// Describe the first HTTP request `change` would send, without any
+    /// network access. Operations that start with a lookup, such as finding
+    /// Cloudflare's zone id, are described by that lookup. Credentials are
+    /// redacted. Not available for RFC 2136.
+    pub fn describe_request<'x>(
+        &self,
+        change: &Change,
+        origin: impl IntoFqdn<'x>,
+    ) -> crate::Result<PlannedRequest> {
+        match self {
+            DnsUpdater::Rfc2136(_) => Err(Error::Api(
+                "RFC 2136 updates are not sent over HTTP".to_string(),
+            )),
+            DnsUpdater::Cloudflare(provider) => Ok(provider.describe_request(origin)),
+            DnsUpdater::Infomaniak(provider) => provider.describe_request(change, origin),
+            DnsUpdater::Dreamhost(provider) => provider.describe_request(change),
+            DnsUpdater::Njalla(provider) => provider.describe_request(change, origin),
+            DnsUpdater::DuckDns(provider) => provider.describe_request(change),
+            DnsUpdater::Multi(updaters) => primary(updaters)?.describe_request(change, origin),
         }
     }
 
@@ -167,82 +883,1496 @@ impl DnsUpdater {
         &self,
         name: impl IntoFqdn<'_>,
         record: DnsRecord,
-        ttl: u32,
+        ttl: impl Into<Ttl>,
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
-        match self {
+        record.validate()?;
+        self.require_support(record.as_type())?;
+        let requested_ttl = ttl.into();
+        let ttl = self.ttl_seconds(requested_ttl);
+        let started = Instant::now();
+        let result = match self {
             DnsUpdater::Rfc2136(provider) => provider.update(name, record, ttl, origin).await,
             DnsUpdater::Cloudflare(provider) => provider.update(name, record, ttl, origin).await,
-        }
+            DnsUpdater::Infomaniak(provider) => provider.update(name, record, ttl, origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.update(name, record, ttl, origin).await,
+            DnsUpdater::Njalla(provider) => provider.update(name, record, ttl, origin).await,
+            DnsUpdater::DuckDns(provider) => provider.update(name, record, ttl, origin).await,
+            DnsUpdater::Multi(updaters) => {
+                let (name, origin) = (name.into_name(), origin.into_name());
+                return fan_out(updaters, |updater| {
+                    updater.run(MultiOp::Update(
+                        name.as_ref(),
+                        record.clone(),
+                        requested_ttl,
+                        origin.as_ref(),
+                    ))
+                })
+                .await;
+            }
+        };
+        self.report(Operation::Update, started, &result);
+        result
     }
 
-    /// Delete an existing DNS record.
-    pub async fn delete(
+    /// Same as [`DnsUpdater::create`], but `subname` is relative to `origin`
+    /// (`www`, or `@` for the zone apex) and is qualified with it before the
+    /// call, so it is never mistaken for a fully qualified name.
+    pub async fn create_relative(
         &self,
-        name: impl IntoFqdn<'_>,
+        subname: &str,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
-        match self {
-            DnsUpdater::Rfc2136(provider) => provider.delete(name, origin).await,
-            DnsUpdater::Cloudflare(provider) => provider.delete(name, origin).await,
-        }
+        let origin = origin.into_name();
+        self.create(
+            qualify_name(subname, origin.as_ref()),
+            record,
+            ttl,
+            origin.as_ref(),
+        )
+        .await
     }
-}
 
-impl<'x> IntoFqdn<'x> for &'x str {
-    fn into_fqdn(self) -> Cow<'x, str> {
-        if self.ends_with('.') {
-            Cow::Borrowed(self)
-        } else {
-            Cow::Owned(format!("{}.", self))
-        }
+    /// Same as [`DnsUpdater::update`], with `subname` relative to `origin` as
+    /// in [`DnsUpdater::create_relative`].
+    pub async fn update_relative(
+        &self,
+        subname: &str,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let origin = origin.into_name();
+        self.update(
+            qualify_name(subname, origin.as_ref()),
+            record,
+            ttl,
+            origin.as_ref(),
+        )
+        .await
     }
 
-    fn into_name(self) -> Cow<'x, str> {
-        if let Some(name) = self.strip_suffix('.') {
-            Cow::Borrowed(name)
-        } else {
-            Cow::Borrowed(self)
-        }
+    /// Same as [`DnsUpdater::delete`], with `subname` relative to `origin` as
+    /// in [`DnsUpdater::create_relative`].
+    pub async fn delete_relative(
+        &self,
+        subname: &str,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let origin = origin.into_name();
+        self.delete(qualify_name(subname, origin.as_ref()), origin.as_ref())
+            .await
     }
-}
 
-impl<'x> IntoFqdn<'x> for &'x String {
-    fn into_fqdn(self) -> Cow<'x, str> {
-        self.as_str().into_fqdn()
+    /// Same as [`DnsUpdater::create`], then read the record back through the
+    /// provider's API and fail with [`Error::Api`] if it is missing or differs.
+    /// This catches writes silently dropped by the provider, it does not check
+    /// DNS propagation.
+    pub async fn create_and_verify(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = name.into_name();
+        let origin = origin.into_name();
+        self.create(name.as_ref(), record.clone(), ttl, origin.as_ref())
+            .await?;
+        self.verify_written(name.as_ref(), &record, origin.as_ref())
+            .await
     }
 
-    fn into_name(self) -> Cow<'x, str> {
-        self.as_str().into_name()
+    /// Same as [`DnsUpdater::update`], then read the record back as in
+    /// [`DnsUpdater::create_and_verify`].
+    pub async fn update_and_verify(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = name.into_name();
+        let origin = origin.into_name();
+        self.update(name.as_ref(), record.clone(), ttl, origin.as_ref())
+            .await?;
+        self.verify_written(name.as_ref(), &record, origin.as_ref())
+            .await
     }
-}
 
-impl<'x> IntoFqdn<'x> for String {
-    fn into_fqdn(self) -> Cow<'x, str> {
-        if self.ends_with('.') {
-            Cow::Owned(self)
+    async fn verify_written(
+        &self,
+        name: &str,
+        record: &DnsRecord,
+        origin: &str,
+    ) -> crate::Result<()> {
+        if self
+            .get_all(name, record.as_type(), origin)
+            .await?
+            .iter()
+            .any(|(existing, _)| existing.content_eq(record))
+        {
+            Ok(())
         } else {
-            Cow::Owned(format!("{}.", self))
+            Err(Error::Api(format!(
+                "{} record at {name} was not found after writing it",
+                record.as_type()
+            )))
         }
     }
 
-    fn into_name(self) -> Cow<'x, str> {
-        if let Some(name) = self.strip_suffix('.') {
-            Cow::Owned(name.to_string())
-        } else {
-            Cow::Owned(self)
+    /// Create a new DNS record with provider specific options. Options that the
+    /// provider does not support are ignored.
+    pub async fn create_with_options(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+        options: CreateOptions,
+    ) -> crate::Result<()> {
+        record.validate()?;
+        self.require_support(record.as_type())?;
+        let ttl = self.ttl_seconds(ttl.into());
+        let started = Instant::now();
+        let result = match self {
+            DnsUpdater::Cloudflare(provider) => {
+                provider
+                    .create_with_options(name, record, ttl, origin, options)
+                    .await
+            }
+            DnsUpdater::Infomaniak(provider) => {
+                provider
+                    .create_with_options(name, record, ttl, origin, options)
+                    .await
+            }
+            // Reported by create.
+            _ => return self.create(name, record, ttl, origin).await,
+        };
+        self.report(Operation::Create, started, &result);
+        result
+    }
+
+    /// Delete every record in a zone carrying `tag`. Only supported by Cloudflare.
+    pub async fn delete_by_tag(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        tag: impl AsRef<str>,
+    ) -> crate::Result<()> {
+        match self {
+            DnsUpdater::Cloudflare(provider) => provider.delete_by_tag(origin, tag.as_ref()).await,
+            _ => Err(Error::Api(
+                "Record tags are not supported by this provider".to_string(),
+            )),
         }
     }
-}
 
-impl FromStr for TsigAlgorithm {
-    type Err = ();
+    /// Create a CNAME record proxied through Cloudflare, e.g. pointing at a
+    /// `<id>.cfargotunnel.com` tunnel. The TTL is left to Cloudflare. Other
+    /// providers create a regular CNAME with a TTL of one hour.
+    pub async fn create_cname_proxied(
+        &self,
+        name: impl IntoFqdn<'_>,
+        target: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let record = DnsRecord::CNAME {
+            content: target.into_name().into_owned(),
+        };
+        match self {
+            DnsUpdater::Cloudflare(_) => {
+                let options = CreateOptions {
+                    proxied: true,
+                    ..Default::default()
+                };
+                self.create_with_options(name, record, Ttl::Auto, origin, options)
+                    .await
+            }
+            _ => self.create(name, record, DEFAULT_TTL, origin).await,
+        }
+    }
 
-    fn from_str(s: &str) -> std::prelude::v1::Result<Self, Self::Err> {
-        match s {
-            "hmac-md5" => Ok(TsigAlgorithm::HmacMd5),
-            "gss" => Ok(TsigAlgorithm::Gss),
-            "hmac-sha1" => Ok(TsigAlgorithm::HmacSha1),
+    /// Point both the zone apex and `www` at `content`, either an IP address
+    /// (A or AAAA records) or a hostname (CNAME records). A CNAME at the apex
+    /// is only possible on Cloudflare, which flattens it.
+    pub async fn create_apex_and_www(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        content: impl AsRef<str>,
+        ttl: impl Into<Ttl>,
+    ) -> crate::Result<()> {
+        let origin = origin.into_name();
+        let ttl = ttl.into();
+        let content = content.as_ref();
+        let record = match content.parse::<IpAddr>() {
+            Ok(ip) => DnsRecord::from_ip(ip),
+            Err(_) if matches!(self, DnsUpdater::Cloudflare(_)) => DnsRecord::CNAME {
+                content: content.to_string(),
+            },
+            Err(_) => {
+                return Err(Error::Api(
+                    "CNAME records at the zone apex are not supported by this provider".to_string(),
+                ))
+            }
+        };
+
+        self.create(origin.as_ref(), record.clone(), ttl, origin.as_ref())
+            .await?;
+        self.create(
+            format!("www.{}", origin.as_ref()),
+            record,
+            ttl,
+            origin.as_ref(),
+        )
+        .await
+    }
+
+    /// Non-generic version of [`DnsUpdater::create`], for callers that cannot use
+    /// `impl IntoFqdn` parameters (boxed futures, trait objects, FFI).
+    pub async fn create_str(
+        &self,
+        name: &str,
+        record: DnsRecord,
+        ttl: Ttl,
+        origin: &str,
+    ) -> crate::Result<()> {
+        self.create(name, record, ttl, origin).await
+    }
+
+    /// Non-generic version of [`DnsUpdater::update`].
+    pub async fn update_str(
+        &self,
+        name: &str,
+        record: DnsRecord,
+        ttl: Ttl,
+        origin: &str,
+    ) -> crate::Result<()> {
+        self.update(name, record, ttl, origin).await
+    }
+
+    /// Non-generic version of [`DnsUpdater::delete`].
+    pub async fn delete_str(&self, name: &str, origin: &str) -> crate::Result<()> {
+        self.delete(name, origin).await
+    }
+
+    /// Create a DNS record at the end of the CNAME chain starting at `name`,
+    /// in the zone the final target belongs to. Useful for ACME challenges
+    /// delegated through a CNAME to another zone.
+    pub async fn create_following_cname(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+    ) -> crate::Result<()> {
+        let target = resolver::follow_cname(name).await?;
+        let origin = resolver::resolve_zone(target.as_str()).await?;
+        self.create(target.as_str(), record, ttl, origin.as_str())
+            .await
+    }
+
+    /// Obtain the content and TTL of an existing DNS record. See
+    /// [`DnsUpdater::ttl_from_seconds`] for Cloudflare's automatic TTL.
+    pub async fn get(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<(DnsRecord, u32)> {
+        let name = name.into_name();
+        self.get_all(name.as_ref(), record_type, origin)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RecordNotFound(name.as_ref().to_string()))
+    }
+
+    /// Obtain the provider's own representation of a record, unmodified, e.g.
+    /// Cloudflare's full record object. RFC 2136 records are converted to a
+    /// JSON object with `name`, `type`, `ttl` and `data` fields.
+    pub async fn get_raw(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<serde_json::Value> {
+        match self {
+            DnsUpdater::Rfc2136(provider) => provider.get_raw(name, record_type, origin).await,
+            DnsUpdater::Cloudflare(provider) => provider.get_raw(name, record_type, origin).await,
+            DnsUpdater::Infomaniak(provider) => provider.get_raw(name, record_type, origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.get_raw(name, record_type, origin).await,
+            DnsUpdater::Njalla(provider) => provider.get_raw(name, record_type, origin).await,
+            DnsUpdater::DuckDns(_) => Err(Error::Api(
+                "Reading records is not supported by DuckDNS".to_string(),
+            )),
+            DnsUpdater::Multi(updaters) => {
+                Box::pin(primary(updaters)?.get_raw(name, record_type, origin)).await
+            }
+        }
+    }
+
+    /// Obtain the content and TTL of every record of a type at a name. See
+    /// [`DnsUpdater::ttl_from_seconds`] for Cloudflare's automatic TTL.
+    pub async fn get_all(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(DnsRecord, u32)>> {
+        match self {
+            DnsUpdater::Rfc2136(provider) => provider.get_all(name, record_type, origin).await,
+            DnsUpdater::Cloudflare(provider) => provider.get_all(name, record_type, origin).await,
+            DnsUpdater::Infomaniak(provider) => provider.get_all(name, record_type, origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.get_all(name, record_type, origin).await,
+            DnsUpdater::Njalla(provider) => provider.get_all(name, record_type, origin).await,
+            DnsUpdater::DuckDns(_) => Err(Error::Api(
+                "Reading records is not supported by DuckDNS".to_string(),
+            )),
+            DnsUpdater::Multi(updaters) => {
+                Box::pin(primary(updaters)?.get_all(name, record_type, origin)).await
+            }
+        }
+    }
+
+    /// The types of the records present at `name`, e.g. to decide what a
+    /// cleanup would remove. Record types not supported by this crate are
+    /// not reported.
+    pub async fn types_at(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<DnsRecordType>> {
+        let name = canonical_name(name.into_name().as_ref());
+        let origin = origin.into_name();
+        let mut types = Vec::new();
+
+        match self {
+            DnsUpdater::Cloudflare(provider) => {
+                return provider.types_at(name.as_str(), origin.as_ref()).await
+            }
+            // Zones can not be listed over RFC 2136, each type is queried instead.
+            DnsUpdater::Rfc2136(_) => {
+                for record_type in DnsRecordType::ALL {
+                    match self
+                        .get_all(name.as_str(), *record_type, origin.as_ref())
+                        .await
+                    {
+                        Ok(records) if !records.is_empty() => types.push(*record_type),
+                        Ok(_) => {}
+                        Err(err) if is_absent(&err) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+            _ => {
+                for (record_name, record, _) in self.list(origin.as_ref()).await? {
+                    let record_type = record.as_type();
+                    if canonical_name(&record_name) == name && !types.contains(&record_type) {
+                        types.push(record_type);
+                    }
+                }
+            }
+        }
+
+        Ok(types)
+    }
+
+    /// Change the TTL of an existing DNS record, keeping its current content.
+    pub async fn set_ttl(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = name.into_name();
+        let origin = origin.into_name();
+        let (record, _) = self
+            .get(name.as_ref(), record_type, origin.as_ref())
+            .await?;
+        self.update(name.as_ref(), record, ttl, origin.as_ref())
+            .await
+    }
+
+    /// Move the records of `record_type` from `old_name` to `new_name`. On
+    /// Cloudflare the records are renamed in place, keeping their IDs and
+    /// metadata. Other providers delete and recreate them.
+    pub async fn rename(
+        &self,
+        old_name: impl IntoFqdn<'_>,
+        new_name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let old_name = old_name.into_name();
+        let new_name = new_name.into_name();
+        let origin = origin.into_name();
+
+        if let DnsUpdater::Cloudflare(provider) = self {
+            return provider
+                .rename(
+                    old_name.as_ref(),
+                    new_name.as_ref(),
+                    record_type,
+                    origin.as_ref(),
+                )
+                .await;
+        }
+
+        let records = self
+            .get_all(old_name.as_ref(), record_type, origin.as_ref())
+            .await?;
+        if records.is_empty() {
+            return Err(Error::RecordNotFound(old_name.into_owned()));
+        }
+        self.delete_rrset(old_name.as_ref(), record_type, origin.as_ref())
+            .await?;
+        for (record, ttl) in records {
+            self.create(new_name.as_ref(), record, ttl, origin.as_ref())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Create a DNS record, or update it if a record of the same type already
+    /// exists at `name`. Nothing is sent if the record is already up to date.
+    /// On RFC 2136 the existing records of that type are replaced.
+    pub async fn upsert(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = name.into_name();
+        let origin = origin.into_name();
+        let ttl = ttl.into();
+        if let DnsUpdater::DuckDns(_) = self {
+            // Records can not be read back from DuckDNS, but every update
+            // replaces the current value.
+            return self
+                .update(name.as_ref(), record, ttl, origin.as_ref())
+                .await;
+        }
+        let existing = match self
+            .get_all(name.as_ref(), record.as_type(), origin.as_ref())
+            .await
+        {
+            Ok(existing) => existing,
+            Err(err) if is_absent(&err) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        if existing.is_empty() {
+            self.create(name.as_ref(), record, ttl, origin.as_ref())
+                .await
+        } else if existing.iter().any(|(current, current_ttl)| {
+            current.content_eq(&record) && *current_ttl == self.ttl_seconds(ttl)
+        }) {
+            Ok(())
+        } else if let DnsUpdater::Rfc2136(provider) = self {
+            // RFC 2136 updates add to the record set, which would keep the
+            // previous value around. The set is swapped for the new record.
+            record.validate()?;
+            self.require_support(record.as_type())?;
+            let started = Instant::now();
+            let current = existing.into_iter().map(|(record, _)| record).collect();
+            let result = provider
+                .replace(
+                    name.as_ref(),
+                    current,
+                    record,
+                    self.ttl_seconds(ttl),
+                    origin.as_ref(),
+                )
+                .await;
+            self.report(Operation::Update, started, &result);
+            result
+        } else {
+            self.update(name.as_ref(), record, ttl, origin.as_ref())
+                .await
+        }
+    }
+
+    /// Update a DNS record unless it already has the same content and TTL.
+    /// Returns `true` if the record was written and `false` if it was already
+    /// up to date.
+    pub async fn update_if_changed(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<bool> {
+        let name = name.into_name();
+        let origin = origin.into_name();
+        let ttl = ttl.into();
+        let unchanged = self
+            .get_all(name.as_ref(), record.as_type(), origin.as_ref())
+            .await?
+            .iter()
+            .any(|(current, current_ttl)| {
+                current.content_eq(&record) && *current_ttl == self.ttl_seconds(ttl)
+            });
+
+        if unchanged {
+            Ok(false)
+        } else {
+            self.update(name.as_ref(), record, ttl, origin.as_ref())
+                .await
+                .map(|_| true)
+        }
+    }
+
+    /// Create a DNS record unless an identical record already exists. Returns
+    /// `true` if the record was created and `false` if it was already present.
+    pub async fn create_if_absent(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<bool> {
+        let name = name.into_name();
+        let origin = origin.into_name();
+        let exists = match self
+            .get_all(name.as_ref(), record.as_type(), origin.as_ref())
+            .await
+        {
+            Ok(existing) => existing
+                .iter()
+                .any(|(current, _)| current.content_eq(&record)),
+            Err(err) if is_absent(&err) => false,
+            Err(err) => return Err(err),
+        };
+
+        if exists {
+            Ok(false)
+        } else {
+            self.create(name.as_ref(), record, ttl, origin.as_ref())
+                .await
+                .map(|_| true)
+        }
+    }
+
+    /// Point `name` at the caller's public IP address, as discovered through
+    /// [`DEFAULT_PUBLIC_IP_ENDPOINT`].
+    pub async fn update_to_public_ip(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+        ttl: impl Into<Ttl>,
+    ) -> crate::Result<IpAddr> {
+        self.update_to_public_ip_using(name, origin, ttl, DEFAULT_PUBLIC_IP_ENDPOINT)
+            .await
+    }
+
+    /// Same as [`DnsUpdater::update_to_public_ip`], using a custom IP discovery
+    /// endpoint. The endpoint may return either a bare IP address (ipify, icanhazip)
+    /// or `key=value` lines containing an `ip=` entry (Cloudflare's `cdn-cgi/trace`).
+    pub async fn update_to_public_ip_using(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+        ttl: impl Into<Ttl>,
+        endpoint: impl AsRef<str>,
+    ) -> crate::Result<IpAddr> {
+        let ip = public_ip(endpoint.as_ref()).await?;
+        self.upsert(name, DnsRecord::from_ip(ip), ttl, origin)
+            .await
+            .map(|_| ip)
+    }
+
+    /// List every record in a zone as `(name, record, ttl)` tuples, with fully
+    /// qualified names. Records of unsupported types are skipped. See
+    /// [`DnsUpdater::ttl_from_seconds`] for Cloudflare's automatic TTL.
+    pub async fn list(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(String, DnsRecord, u32)>> {
+        match self {
+            DnsUpdater::Rfc2136(_) => Err(Error::Api(
+                "Listing records is not supported by RFC 2136".to_string(),
+            )),
+            DnsUpdater::Cloudflare(provider) => provider.list(origin).await,
+            DnsUpdater::Infomaniak(provider) => provider.list(origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.list(origin).await,
+            DnsUpdater::Njalla(provider) => provider.list(origin).await,
+            DnsUpdater::DuckDns(_) => Err(Error::Api(
+                "Listing records is not supported by DuckDNS".to_string(),
+            )),
+            DnsUpdater::Multi(updaters) => Box::pin(primary(updaters)?.list(origin)).await,
+        }
+    }
+
+    /// Update the refresh, retry, expire and minimum (negative caching) TTL of
+    /// a zone's SOA record. Only supported by RFC 2136, the other providers
+    /// manage the SOA record themselves.
+    pub async fn set_zone_soa(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        params: SoaParams,
+    ) -> crate::Result<()> {
+        match self {
+            DnsUpdater::Rfc2136(provider) => provider.set_zone_soa(origin, params).await,
+            DnsUpdater::Cloudflare(_)
+            | DnsUpdater::Infomaniak(_)
+            | DnsUpdater::Dreamhost(_)
+            | DnsUpdater::Njalla(_)
+            | DnsUpdater::DuckDns(_)
+            | DnsUpdater::Multi(_) => Err(Error::Api(
+                "SOA management is not supported by this provider".to_string(),
+            )),
+        }
+    }
+
+    /// List the names of all zones available to this account.
+    pub async fn list_zones(&self) -> crate::Result<Vec<String>> {
+        match self {
+            DnsUpdater::Rfc2136(_) => Err(Error::Api(
+                "Listing zones is not supported by RFC 2136".to_string(),
+            )),
+            DnsUpdater::Cloudflare(provider) => provider.list_zones().await,
+            DnsUpdater::Infomaniak(_) => Err(Error::Api(
+                "Listing zones is not supported by Infomaniak".to_string(),
+            )),
+            DnsUpdater::Dreamhost(provider) => provider.list_zones().await,
+            DnsUpdater::Njalla(provider) => provider.list_zones().await,
+            DnsUpdater::DuckDns(_) => Err(Error::Api(
+                "Listing zones is not supported by DuckDNS".to_string(),
+            )),
+            DnsUpdater::Multi(updaters) => Box::pin(primary(updaters)?.list_zones()).await,
+        }
+    }
+
+    /// The nameservers assigned to a zone, e.g. to configure the delegation
+    /// at the registrar. Cloudflare reports them through its API, other
+    /// providers fall back to a live NS query.
+    pub async fn zone_nameservers(&self, origin: impl IntoFqdn<'_>) -> crate::Result<Vec<String>> {
+        match self {
+            DnsUpdater::Cloudflare(provider) => provider.zone_nameservers(origin).await,
+            _ => resolver::zone_nameservers(origin).await,
+        }
+    }
+
+    /// Stream every record of every zone as `(zone, name, record, ttl)`.
+    /// Zones are fetched one at a time, so only a single zone is held in
+    /// memory at any point.
+    pub fn stream_records(
+        &self,
+    ) -> impl Stream<Item = crate::Result<(String, String, DnsRecord, u32)>> + '_ {
+        stream::once(self.list_zones())
+            .map_ok(|zones| stream::iter(zones.into_iter().map(Ok::<_, Error>)))
+            .try_flatten()
+            .and_then(move |zone: String| async move {
+                let records = self.list(zone.as_str()).await?;
+                Ok((zone, records))
+            })
+            .map_ok(|(zone, records)| {
+                stream::iter(
+                    records
+                        .into_iter()
+                        .map(move |(name, record, ttl)| Ok((zone.clone(), name, record, ttl))),
+                )
+            })
+            .try_flatten()
+    }
+
+    /// Delete every record in a zone for which `predicate` returns `true`, e.g.
+    /// leftover `_acme-challenge.` TXT records. The predicate receives the fully
+    /// qualified name and the record. Returns the number of records deleted.
+    pub async fn cleanup(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        predicate: impl Fn(&str, &DnsRecord) -> bool,
+    ) -> crate::Result<usize> {
+        let origin = origin.into_name();
+        let mut deleted = 0;
+
+        for (name, record, _) in self.list(origin.as_ref()).await? {
+            if predicate(&name, &record) {
+                self.delete_value(name.as_str(), record, origin.as_ref())
+                    .await?;
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Apply a set of changes to a zone. On Cloudflare the whole batch is sent
+    /// in a single atomic request. Other providers apply the deletions, updates
+    /// and creations one by one, stopping at the first error.
+    pub async fn apply_batch(&self, origin: impl IntoFqdn<'_>, batch: Batch) -> crate::Result<()> {
+        for (_, record, _) in batch.creates.iter().chain(&batch.updates) {
+            record.validate()?;
+            self.require_support(record.as_type())?;
+        }
+
+        let origin = origin.into_name();
+        match self {
+            DnsUpdater::Cloudflare(provider) => provider.apply_batch(origin.as_ref(), batch).await,
+            _ => {
+                for name in batch.deletes {
+                    self.delete(name, origin.as_ref()).await?;
+                }
+                for (name, record, ttl) in batch.updates {
+                    self.update(name, record, ttl, origin.as_ref()).await?;
+                }
+                for (name, record, ttl) in batch.creates {
+                    self.create(name, record, ttl, origin.as_ref()).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Compute the changes that bring a zone from its current records to
+    /// `desired`, given as `(name, record, ttl)` tuples, without applying them.
+    /// Records missing from `desired` are deleted, except the NS records at the
+    /// zone apex. A record whose name and type hold a single value on both
+    /// sides is updated in place, other differences become deletions and
+    /// creations. Changes are ordered as deletions, updates and creations.
+    /// TTLs are compared as the provider stores them, so a desired
+    /// [`Ttl::Auto`] matches Cloudflare's automatic TTL.
+    pub async fn plan(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        desired: Vec<(String, DnsRecord, impl Into<Ttl>)>,
+    ) -> crate::Result<Vec<PlannedChange>> {
+        for (_, record, _) in &desired {
+            record.validate()?;
+            self.require_support(record.as_type())?;
+        }
+
+        let origin = origin.into_name();
+        let zone = canonical_name(origin.as_ref());
+        let mut current = self
+            .list(origin.as_ref())
+            .await?
+            .into_iter()
+            .map(|(name, record, ttl)| (canonical_name(&name), record, ttl))
+            .collect::<Vec<_>>();
+        let mut desired = desired
+            .into_iter()
+            .map(|(name, record, ttl)| (canonical_name(&name), record, ttl.into()))
+            .collect::<Vec<_>>();
+
+        // The apex NS records are usually managed by the provider, leave them
+        // alone unless `desired` lists some.
+        let manage_apex_ns = desired
+            .iter()
+            .any(|(name, record, _)| name == &zone && record.as_type() == DnsRecordType::NS);
+        current.retain(|(name, record, _)| {
+            manage_apex_ns || name != &zone || record.as_type() != DnsRecordType::NS
+        });
+
+        // Drop the records that are already in place.
+        desired.retain(|(name, record, ttl)| {
+            match current
+                .iter()
+                .position(|(current_name, current_record, current_ttl)| {
+                    current_name == name
+                        && current_record.content_eq(record)
+                        && *current_ttl == self.ttl_seconds(*ttl)
+                }) {
+                Some(pos) => {
+                    current.remove(pos);
+                    false
+                }
+                None => true,
+            }
+        });
+
+        fn count<T>(
+            records: &[(String, DnsRecord, T)],
+            name: &str,
+            record_type: DnsRecordType,
+        ) -> usize {
+            records
+                .iter()
+                .filter(|(other, record, _)| other == name && record.as_type() == record_type)
+                .count()
+        }
+        let mut deletes = Vec::new();
+        let mut updates = Vec::new();
+        let mut creates = Vec::new();
+
+        for (name, record, ttl) in &desired {
+            let record_type = record.as_type();
+            if count(&desired, name, record_type) == 1 && count(&current, name, record_type) == 1 {
+                updates.push(PlannedChange::Update {
+                    name: name.clone(),
+                    record: record.clone(),
+                    ttl: *ttl,
+                });
+            } else {
+                creates.push(PlannedChange::Create {
+                    name: name.clone(),
+                    record: record.clone(),
+                    ttl: *ttl,
+                });
+            }
+        }
+        for (name, record, _) in &current {
+            let record_type = record.as_type();
+            if count(&desired, name, record_type) != 1 || count(&current, name, record_type) != 1 {
+                deletes.push(PlannedChange::Delete {
+                    name: name.clone(),
+                    record: record.clone(),
+                });
+            }
+        }
+
+        deletes.extend(updates);
+        deletes.extend(creates);
+        Ok(deletes)
+    }
+
+    /// Apply the changes computed by [`DnsUpdater::plan`] in order, stopping at
+    /// the first error.
+    pub async fn apply(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        plan: Vec<PlannedChange>,
+    ) -> crate::Result<()> {
+        let origin = origin.into_name();
+        for change in plan {
+            match change {
+                PlannedChange::Create { name, record, ttl } => {
+                    self.create(name, record, ttl, origin.as_ref()).await?
+                }
+                PlannedChange::Update { name, record, ttl } => {
+                    self.update(name, record, ttl, origin.as_ref()).await?
+                }
+                PlannedChange::Delete { name, record } => {
+                    self.delete_value(name, record, origin.as_ref()).await?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete every record of `record_type` in a zone, e.g. stale TXT records,
+    /// returning the name and deletion result of each record found.
+    pub async fn delete_all_of_type(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+    ) -> crate::Result<Vec<(String, crate::Result<()>)>> {
+        let origin = origin.into_name();
+        let mut results = Vec::new();
+
+        for (name, record, _) in self.list(origin.as_ref()).await? {
+            if record.as_type() == record_type {
+                let result = self
+                    .delete_value(name.as_str(), record, origin.as_ref())
+                    .await;
+                results.push((name, result));
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Export every record of a zone, e.g. to replay it on another provider
+    /// with [`DnsUpdater::import_zone`].
+    pub async fn export_zone(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<ExportedRecord>> {
+        self.list(origin).await.map(|records| {
+            records
+                .into_iter()
+                .map(|(name, record, ttl)| ExportedRecord { name, record, ttl })
+                .collect()
+        })
+    }
+
+    /// Create every exported record in a zone, returning the result of each
+    /// creation in the same order as `records`.
+    pub async fn import_zone(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        records: Vec<ExportedRecord>,
+    ) -> Vec<crate::Result<()>> {
+        self.import_zone_cancellable(origin, records, std::future::pending())
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Same as [`DnsUpdater::import_zone`], but stops with [`Error::Cancelled`]
+    /// as soon as `cancel` completes (e.g. a `CancellationToken::cancelled()`
+    /// future or a deadline `sleep`).
+    pub async fn import_zone_cancellable(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        records: Vec<ExportedRecord>,
+        cancel: impl Future<Output = ()>,
+    ) -> crate::Result<Vec<crate::Result<()>>> {
+        let origin = origin.into_name();
+        let mut results = Vec::with_capacity(records.len());
+        tokio::pin!(cancel);
+
+        for record in records {
+            tokio::select! {
+                biased;
+                _ = &mut cancel => return Err(Error::Cancelled),
+                result = self.create(record.name, record.record, record.ttl, origin.as_ref()) => {
+                    results.push(result);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Check whether the zone's CAA records allow `ca_domain` (e.g. `letsencrypt.org`)
+    /// to issue certificates. If the zone has no CAA records, a `0 issue "<ca_domain>"`
+    /// record is created at the apex.
+    pub async fn ensure_caa_allows(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        ca_domain: impl AsRef<str>,
+    ) -> crate::Result<CaaStatus> {
+        let origin = origin.into_name();
+        let ca_domain = ca_domain.as_ref();
+        let records = self
+            .get_all(origin.as_ref(), DnsRecordType::CAA, origin.as_ref())
+            .await?
+            .into_iter()
+            .map(|(record, _)| record)
+            .collect::<Vec<_>>();
+
+        if records.is_empty() {
+            let record = DnsRecord::CAA {
+                flags: 0,
+                tag: "issue".to_string(),
+                value: ca_domain.to_string(),
+            };
+            self.create(
+                origin.as_ref(),
+                record.clone(),
+                DEFAULT_TTL,
+                origin.as_ref(),
+            )
+            .await?;
+
+            return Ok(CaaStatus {
+                records: vec![record],
+                allowed: true,
+                created: true,
+            });
+        }
+
+        // Without any "issue" property, issuance is not restricted (RFC 8659, section 4.2).
+        let mut issuers = records
+            .iter()
+            .filter_map(|record| match record {
+                DnsRecord::CAA { tag, value, .. } if tag.eq_ignore_ascii_case("issue") => {
+                    Some(value.split(';').next().unwrap_or_default().trim())
+                }
+                _ => None,
+            })
+            .peekable();
+        let allowed = issuers.peek().is_none()
+            || issuers.any(|issuer| issuer.eq_ignore_ascii_case(ca_domain));
+
+        Ok(CaaStatus {
+            records,
+            allowed,
+            created: false,
+        })
+    }
+
+    /// Enable DNSSEC signing on a zone, returning the DS data to publish at the registrar.
+    pub async fn enable_dnssec(&self, origin: impl IntoFqdn<'_>) -> crate::Result<DnssecKey> {
+        match self {
+            DnsUpdater::Cloudflare(provider) => provider.enable_dnssec(origin).await,
+            DnsUpdater::Rfc2136(_)
+            | DnsUpdater::Infomaniak(_)
+            | DnsUpdater::Njalla(_)
+            | DnsUpdater::Dreamhost(_)
+            | DnsUpdater::DuckDns(_)
+            | DnsUpdater::Multi(_) => Err(Error::Api(
+                "DNSSEC management is not supported by this provider".to_string(),
+            )),
+        }
+    }
+
+    /// Disable DNSSEC signing on a zone.
+    pub async fn disable_dnssec(&self, origin: impl IntoFqdn<'_>) -> crate::Result<()> {
+        match self {
+            DnsUpdater::Cloudflare(provider) => provider.disable_dnssec(origin).await,
+            DnsUpdater::Rfc2136(_)
+            | DnsUpdater::Infomaniak(_)
+            | DnsUpdater::Njalla(_)
+            | DnsUpdater::Dreamhost(_)
+            | DnsUpdater::DuckDns(_)
+            | DnsUpdater::Multi(_) => Err(Error::Api(
+                "DNSSEC management is not supported by this provider".to_string(),
+            )),
+        }
+    }
+
+    /// Delete an existing DNS record.
+    pub async fn delete(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let started = Instant::now();
+        let result = match self {
+            DnsUpdater::Rfc2136(provider) => provider.delete(name, origin).await,
+            DnsUpdater::Cloudflare(provider) => provider.delete(name, origin).await,
+            DnsUpdater::Infomaniak(provider) => provider.delete(name, origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.delete(name, origin).await,
+            DnsUpdater::Njalla(provider) => provider.delete(name, origin).await,
+            DnsUpdater::DuckDns(provider) => provider.delete(name, origin).await,
+            DnsUpdater::Multi(updaters) => {
+                let (name, origin) = (name.into_name(), origin.into_name());
+                return fan_out(updaters, |updater| {
+                    updater.run(MultiOp::Delete(name.as_ref(), origin.as_ref()))
+                })
+                .await;
+            }
+        };
+        self.report(Operation::Delete, started, &result);
+        result
+    }
+
+    /// Delete every record of `record_type` at `name`, leaving records of
+    /// other types at the same name in place.
+    pub async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let started = Instant::now();
+        let result = match self {
+            DnsUpdater::Rfc2136(provider) => provider.delete_rrset(name, record_type, origin).await,
+            DnsUpdater::Cloudflare(provider) => {
+                provider.delete_rrset(name, record_type, origin).await
+            }
+            DnsUpdater::Infomaniak(provider) => {
+                provider.delete_rrset(name, record_type, origin).await
+            }
+            DnsUpdater::Dreamhost(provider) => {
+                provider.delete_rrset(name, record_type, origin).await
+            }
+            DnsUpdater::Njalla(provider) => provider.delete_rrset(name, record_type, origin).await,
+            DnsUpdater::DuckDns(provider) => provider.delete_rrset(name, record_type, origin).await,
+            DnsUpdater::Multi(updaters) => {
+                let (name, origin) = (name.into_name(), origin.into_name());
+                return fan_out(updaters, |updater| {
+                    updater.run(MultiOp::DeleteRrset(
+                        name.as_ref(),
+                        record_type,
+                        origin.as_ref(),
+                    ))
+                })
+                .await;
+            }
+        };
+        self.report(Operation::Delete, started, &result);
+        result
+    }
+
+    /// Delete the record at `name` whose content matches `record`, leaving
+    /// other records of the same name and type in place, e.g. a single ACME
+    /// challenge among several TXT records.
+    pub async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let started = Instant::now();
+        let result = match self {
+            DnsUpdater::Rfc2136(provider) => provider.delete_value(name, record, origin).await,
+            DnsUpdater::Cloudflare(provider) => provider.delete_value(name, &record, origin).await,
+            DnsUpdater::Infomaniak(provider) => provider.delete_value(name, &record, origin).await,
+            DnsUpdater::Dreamhost(provider) => provider.delete_value(name, &record, origin).await,
+            DnsUpdater::Njalla(provider) => provider.delete_value(name, &record, origin).await,
+            DnsUpdater::DuckDns(provider) => provider.delete_value(name, &record, origin).await,
+            DnsUpdater::Multi(updaters) => {
+                let (name, origin) = (name.into_name(), origin.into_name());
+                return fan_out(updaters, |updater| {
+                    updater.run(MultiOp::DeleteValue(
+                        name.as_ref(),
+                        record.clone(),
+                        origin.as_ref(),
+                    ))
+                })
+                .await;
+            }
+        };
+        self.report(Operation::Delete, started, &result);
+        result
+    }
+}
+
+impl Batch {
+    pub fn create<'x>(
+        mut self,
+        name: impl IntoFqdn<'x>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+    ) -> Self {
+        self.creates
+            .push((name.into_name().into_owned(), record, ttl.into()));
+        self
+    }
+
+    pub fn update<'x>(
+        mut self,
+        name: impl IntoFqdn<'x>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+    ) -> Self {
+        self.updates
+            .push((name.into_name().into_owned(), record, ttl.into()));
+        self
+    }
+
+    pub fn delete<'x>(mut self, name: impl IntoFqdn<'x>) -> Self {
+        self.deletes.push(name.into_name().into_owned());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.creates.is_empty() && self.updates.is_empty() && self.deletes.is_empty()
+    }
+}
+
+impl ZoneUpdater {
+    /// The zone this handle operates on.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Create a new DNS record in this zone.
+    pub async fn create(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+    ) -> crate::Result<()> {
+        self.updater
+            .create(name, record, ttl, self.origin.as_str())
+            .await
+    }
+
+    /// Update an existing DNS record in this zone.
+    pub async fn update(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: impl Into<Ttl>,
+    ) -> crate::Result<()> {
+        self.updater
+            .update(name, record, ttl, self.origin.as_str())
+            .await
+    }
+
+    /// Delete an existing DNS record in this zone.
+    pub async fn delete(&self, name: impl IntoFqdn<'_>) -> crate::Result<()> {
+        self.updater.delete(name, self.origin.as_str()).await
+    }
+}
+
+impl<T: Clone> ApiCacheManager<T> {
+    /// Return the cached value for `key`, fetching and storing it on a miss.
+    pub async fn get_or_update<F, Fut>(&self, key: &str, fetch: F) -> crate::Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = crate::Result<T>>,
+    {
+        let cell = match self.entries.lock() {
+            Ok(mut entries) => entries.entry(key.to_string()).or_default().clone(),
+            Err(_) => Arc::new(OnceCell::new()),
+        };
+
+        // Failed fetches leave the cell empty, so the next caller retries.
+        cell.get_or_try_init(fetch).await.cloned()
+    }
+
+    /// Same as [`ApiCacheManager::get_or_update`], but retries a few times when
+    /// the fetch fails with [`Error::RecordNotFound`]. Some APIs are eventually
+    /// consistent and may not list a record right after it was created.
+    pub async fn get_or_update_retrying<F, Fut>(&self, key: &str, fetch: F) -> crate::Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = crate::Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match self.get_or_update(key, &fetch).await {
+                Err(Error::RecordNotFound(_)) if attempt < NOT_FOUND_RETRIES => {
+                    attempt += 1;
+                    self.invalidate(key);
+                    tokio::time::sleep(NOT_FOUND_BACKOFF * attempt).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Remove a cached value, forcing the next lookup to hit the API.
+    pub fn invalidate(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+    }
+}
+
+impl<T> Default for ApiCacheManager<T> {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+/// An operation fanned out to the updaters of a [`DnsUpdater::Multi`].
+enum MultiOp<'x> {
+    Create(&'x str, DnsRecord, Ttl, &'x str),
+    Update(&'x str, DnsRecord, Ttl, &'x str),
+    Delete(&'x str, &'x str),
+    DeleteRrset(&'x str, DnsRecordType, &'x str),
+    DeleteValue(&'x str, DnsRecord, &'x str),
+}
+
+impl DnsUpdater {
+    // Boxed outside of the operations themselves, whose futures would
+    // otherwise contain themselves.
+    fn run<'x>(&'x self, op: MultiOp<'x>) -> BoxFuture<'x, crate::Result<()>> {
+        match op {
+            MultiOp::Create(name, record, ttl, origin) => {
+                self.create(name, record, ttl, origin).boxed()
+            }
+            MultiOp::Update(name, record, ttl, origin) => {
+                self.update(name, record, ttl, origin).boxed()
+            }
+            MultiOp::Delete(name, origin) => self.delete(name, origin).boxed(),
+            MultiOp::DeleteRrset(name, record_type, origin) => {
+                self.delete_rrset(name, record_type, origin).boxed()
+            }
+            MultiOp::DeleteValue(name, record, origin) => {
+                self.delete_value(name, record, origin).boxed()
+            }
+        }
+    }
+}
+
+/// The updater serving reads for a [`DnsUpdater::Multi`].
+fn primary(updaters: &[DnsUpdater]) -> crate::Result<&DnsUpdater> {
+    updaters
+        .first()
+        .ok_or_else(|| Error::Config("no updaters given".to_string()))
+}
+
+/// Whether a lookup failed because nothing exists at the name, such as an
+/// NXDOMAIN answer from an RFC 2136 server.
+fn is_absent(err: &Error) -> bool {
+    match err {
+        Error::NotFound => true,
+        Error::Response(code) => code == ResponseCode::NXDomain.to_str(),
+        _ => false,
+    }
+}
+
+/// Run `op` on every updater of a [`DnsUpdater::Multi`] concurrently, failing
+/// with the errors of all updaters that did not succeed.
+async fn fan_out<'x>(
+    updaters: &'x [DnsUpdater],
+    op: impl Fn(&'x DnsUpdater) -> BoxFuture<'x, crate::Result<()>>,
+) -> crate::Result<()> {
+    let errors = future::join_all(updaters.iter().map(op))
+        .await
+        .into_iter()
+        .zip(updaters)
+        .filter_map(|(result, updater)| {
+            result
+                .err()
+                .map(|err| format!("{}: {err}", updater.provider_name()))
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Api(format!(
+            "{} of {} updaters failed: {}",
+            errors.len(),
+            updaters.len(),
+            errors.join("; ")
+        )))
+    }
+}
+
+async fn public_ip(endpoint: &str) -> crate::Result<IpAddr> {
+    let response = HttpClientBuilder::default()
+        .get(endpoint)
+        .send_raw()
+        .await?;
+    let response = response.trim();
+
+    response
+        .parse()
+        .ok()
+        .or_else(|| {
+            response
+                .lines()
+                .find_map(|line| line.strip_prefix("ip="))
+                .and_then(|ip| ip.trim().parse().ok())
+        })
+        .ok_or_else(|| Error::Parse(format!("No IP address found in response from {endpoint}")))
+}
+
+/// Strip the origin from a name, returning the zone-relative host name in
+/// [`canonical_name`] form (`@` for the zone apex).
+pub fn strip_origin_from_name(name: &str, origin: &str) -> String {
+    let mut name = canonical_name(name);
+    let origin = canonical_name(origin);
+
+    if name == origin {
+        return "@".to_string();
+    }
+
+    let pos = name.len().saturating_sub(origin.len() + 1);
+    if pos > 0 && name.as_bytes()[pos] == b'.' && name[pos + 1..] == origin {
+        name.truncate(pos);
+    }
+    name
+}
+
+/// Canonical form of a DNS name, used whenever names returned by a provider
+/// are matched against the caller's input: lowercase and without the trailing
+/// dot. Use [`IntoFqdn::into_fqdn`] instead where the wire protocol requires a
+/// fully qualified name.
+pub fn canonical_name(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Build the reverse lookup name of an IP address, such as
+/// `1.0.0.127.in-addr.arpa` for `127.0.0.1`. IPv6 addresses are expanded and
+/// nibble-reversed under `ip6.arpa`.
+pub fn reverse_dns_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::with_capacity(72);
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Qualify a zone-relative host name (`@` for the zone apex) with its origin.
+pub(crate) fn qualify_name(host: &str, origin: &str) -> String {
+    let origin = origin.trim_end_matches('.');
+    match host.trim_end_matches('.') {
+        "" | "@" => origin.to_string(),
+        host => format!("{host}.{origin}"),
+    }
+}
+
+impl<'x> IntoFqdn<'x> for &'x str {
+    fn into_fqdn(self) -> Cow<'x, str> {
+        if self.ends_with('.') {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(format!("{}.", self))
+        }
+    }
+
+    fn into_name(self) -> Cow<'x, str> {
+        if let Some(name) = self.strip_suffix('.') {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Borrowed(self)
+        }
+    }
+}
+
+impl<'x> IntoFqdn<'x> for &'x String {
+    fn into_fqdn(self) -> Cow<'x, str> {
+        self.as_str().into_fqdn()
+    }
+
+    fn into_name(self) -> Cow<'x, str> {
+        self.as_str().into_name()
+    }
+}
+
+impl<'x> IntoFqdn<'x> for String {
+    fn into_fqdn(mut self) -> Cow<'x, str> {
+        // Reuse the caller's buffer instead of formatting a new string.
+        if !self.ends_with('.') {
+            self.push('.');
+        }
+        Cow::Owned(self)
+    }
+
+    fn into_name(mut self) -> Cow<'x, str> {
+        if self.ends_with('.') {
+            self.pop();
+        }
+        Cow::Owned(self)
+    }
+}
+
+impl TsigAlgorithm {
+    /// The names accepted by [`TsigAlgorithm::from_str`].
+    pub fn all() -> &'static [&'static str] {
+        &[
+            "hmac-md5",
+            "gss",
+            "hmac-sha1",
+            "hmac-sha224",
+            "hmac-sha256",
+            "hmac-sha256-128",
+            "hmac-sha384",
+            "hmac-sha384-192",
+            "hmac-sha512",
+            "hmac-sha512-256",
+        ]
+    }
+}
+
+impl From<u32> for Ttl {
+    fn from(seconds: u32) -> Self {
+        Ttl::Seconds(seconds)
+    }
+}
+
+impl From<&str> for Target {
+    fn from(name: &str) -> Self {
+        if name.contains('.') {
+            Target::Absolute(name.trim_end_matches('.').to_string())
+        } else {
+            Target::Relative(name.to_string())
+        }
+    }
+}
+
+impl FromStr for TsigAlgorithm {
+    type Err = Error;
+
+    /// Parse an algorithm name, ignoring case and accepting `_` in place of `-`.
+    fn from_str(s: &str) -> std::prelude::v1::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "hmac-md5" => Ok(TsigAlgorithm::HmacMd5),
+            "gss" => Ok(TsigAlgorithm::Gss),
+            "hmac-sha1" => Ok(TsigAlgorithm::HmacSha1),
             "hmac-sha224" => Ok(TsigAlgorithm::HmacSha224),
             "hmac-sha256" => Ok(TsigAlgorithm::HmacSha256),
             "hmac-sha256-128" => Ok(TsigAlgorithm::HmacSha256_128),
@@ -250,22 +2380,1524 @@ impl FromStr for TsigAlgorithm {
             "hmac-sha384-192" => Ok(TsigAlgorithm::HmacSha384_192),
             "hmac-sha512" => Ok(TsigAlgorithm::HmacSha512),
             "hmac-sha512-256" => Ok(TsigAlgorithm::HmacSha512_256),
+            _ => Err(Error::Parse(format!(
+                "Unknown TSIG algorithm {s:?}, expected one of: {}",
+                TsigAlgorithm::all().join(", ")
+            ))),
+        }
+    }
+}
+
+impl DnsRecordType {
+    /// Every record type supported by this crate.
+    pub const ALL: &'static [DnsRecordType] = &[
+        DnsRecordType::A,
+        DnsRecordType::AAAA,
+        DnsRecordType::CNAME,
+        DnsRecordType::NS,
+        DnsRecordType::MX,
+        DnsRecordType::TXT,
+        DnsRecordType::SRV,
+        DnsRecordType::CAA,
+        DnsRecordType::CERT,
+        DnsRecordType::SMIMEA,
+        DnsRecordType::OPENPGPKEY,
+        DnsRecordType::SPF,
+    ];
+
+    /// Record types supported by every provider.
+    pub(crate) const BASIC: &'static [DnsRecordType] = &[
+        DnsRecordType::A,
+        DnsRecordType::AAAA,
+        DnsRecordType::CNAME,
+        DnsRecordType::NS,
+        DnsRecordType::MX,
+        DnsRecordType::TXT,
+        DnsRecordType::SRV,
+        DnsRecordType::CAA,
+    ];
+
+    /// The address record type matching the family of `ip`.
+    pub fn for_ip(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => DnsRecordType::A,
+            IpAddr::V6(_) => DnsRecordType::AAAA,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DnsRecordType::A => "A",
+            DnsRecordType::AAAA => "AAAA",
+            DnsRecordType::CNAME => "CNAME",
+            DnsRecordType::NS => "NS",
+            DnsRecordType::MX => "MX",
+            DnsRecordType::TXT => "TXT",
+            DnsRecordType::SRV => "SRV",
+            DnsRecordType::CAA => "CAA",
+            DnsRecordType::CERT => "CERT",
+            DnsRecordType::SMIMEA => "SMIMEA",
+            DnsRecordType::OPENPGPKEY => "OPENPGPKEY",
+            DnsRecordType::SPF => "SPF",
+        }
+    }
+}
+
+impl FromStr for DnsRecordType {
+    type Err = ();
+
+    fn from_str(s: &str) -> std::prelude::v1::Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(DnsRecordType::A),
+            "AAAA" => Ok(DnsRecordType::AAAA),
+            "CNAME" => Ok(DnsRecordType::CNAME),
+            "NS" => Ok(DnsRecordType::NS),
+            "MX" => Ok(DnsRecordType::MX),
+            "TXT" => Ok(DnsRecordType::TXT),
+            "SRV" => Ok(DnsRecordType::SRV),
+            "CAA" => Ok(DnsRecordType::CAA),
+            "CERT" => Ok(DnsRecordType::CERT),
+            "SMIMEA" => Ok(DnsRecordType::SMIMEA),
+            "OPENPGPKEY" => Ok(DnsRecordType::OPENPGPKEY),
+            "SPF" => Ok(DnsRecordType::SPF),
             _ => Err(()),
         }
     }
 }
 
+impl Display for DnsRecordType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Absolute(name) => write!(f, "{name}."),
+            Target::Relative(name) => f.write_str(name),
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Error::Protocol(e) => write!(f, "Protocol error: {}", e),
             Error::Parse(e) => write!(f, "Parse error: {}", e),
+            Error::Config(e) => write!(f, "Configuration error: {}", e),
             Error::Client(e) => write!(f, "Client error: {}", e),
             Error::Response(e) => write!(f, "Response error: {}", e),
             Error::Api(e) => write!(f, "API error: {}", e),
             Error::Serialize(e) => write!(f, "Serialize error: {}", e),
             Error::Unauthorized => write!(f, "Unauthorized"),
             Error::NotFound => write!(f, "Not found"),
+            Error::ZoneNotFound(zone) => write!(f, "Zone {} not found", zone),
+            Error::RecordNotFound(name) => write!(f, "DNS record {} not found", name),
+            Error::Cancelled => write!(f, "Operation cancelled"),
+            Error::Timeout(e) => write!(f, "Timed out: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    use futures_util::{future::join_all, TryStreamExt};
+    use serde_json::json;
+
+    use crate::{
+        canonical_name,
+        http::tests::{MockResponse, MockRoute, MockServer, TEST_CA},
+        resolver::tests::mock_dns,
+        reverse_dns_name, strip_origin_from_name, ApiCacheManager, Change, CreateOptions,
+        DnsRecord, DnsRecordType, DnsUpdater, Error, ExportedRecord, IntoFqdn, Operation,
+        OperationMetric, PlannedChange, Target, TsigAlgorithm, Ttl, DEFAULT_TTL,
+    };
+
+    const INFOMANIAK_RECORDS: &str = "/2/zones/example.com/records";
+
+    fn infomaniak(server: &MockServer) -> DnsUpdater {
+        DnsUpdater::new_infomaniak("token", None)
+            .unwrap()
+            .map_http_client(&|client| client.with_base_url(&server.url))
+    }
+
+    fn infomaniak_success(data: serde_json::Value) -> MockResponse {
+        MockResponse::Json(200, json!({ "result": "success", "data": data }))
+    }
+
+    fn cloudflare(server: &MockServer) -> DnsUpdater {
+        DnsUpdater::new_cloudflare("token", None::<String>, None)
+            .unwrap()
+            .map_http_client(&|client| client.with_base_url(&server.url))
+    }
+
+    fn cloudflare_ok(result: serde_json::Value) -> MockResponse {
+        MockResponse::Json(
+            200,
+            json!({ "success": true, "errors": [], "result": result }),
+        )
+    }
+
+    fn cloudflare_zone() -> MockRoute {
+        MockRoute::new(
+            "GET",
+            "/client/v4/zones?",
+            cloudflare_ok(json!([{ "id": "z1", "name": "example.com" }])),
+        )
+    }
+
+    fn rfc2136() -> DnsUpdater {
+        DnsUpdater::new_rfc2136_tsig(
+            "udp://127.0.0.1:53",
+            "key",
+            b"secret".to_vec(),
+            TsigAlgorithm::HmacSha256,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn capabilities() {
+        let rfc2136 = rfc2136().capabilities();
+        assert!(!rfc2136.supports_list);
+        assert!(rfc2136.supports_multi_value);
+        assert!(rfc2136.supports(DnsRecordType::SPF));
+
+        let cloudflare = DnsUpdater::new_cloudflare("token", None::<String>, None)
+            .unwrap()
+            .capabilities();
+        assert!(cloudflare.supports_list);
+        assert!(!cloudflare.supports(DnsRecordType::SPF));
+    }
+
+    #[test]
+    fn multi_capabilities_combine_updaters() {
+        let multi = DnsUpdater::new_multi(vec![
+            DnsUpdater::new_cloudflare("token", None::<String>, None).unwrap(),
+            DnsUpdater::new_duckdns("token", None).unwrap(),
+        ])
+        .unwrap()
+        .capabilities();
+        assert_eq!(
+            multi.record_types.as_ref(),
+            [DnsRecordType::A, DnsRecordType::AAAA, DnsRecordType::TXT]
+        );
+        assert!(multi.supports_list);
+        assert!(!multi.supports_multi_value);
+
+        let multi = DnsUpdater::new_multi(vec![
+            rfc2136(),
+            DnsUpdater::new_cloudflare("token", None::<String>, None).unwrap(),
+        ])
+        .unwrap()
+        .capabilities();
+        assert!(!multi.supports_list);
+        assert!(multi.supports_multi_value);
+    }
+
+    #[tokio::test]
+    async fn create_with_options_rejects_unsupported_types() {
+        let updater = DnsUpdater::new_cloudflare("token", None::<String>, None).unwrap();
+        let result = updater
+            .create_with_options(
+                "example.com",
+                DnsRecord::SPF {
+                    content: "v=spf1 -all".to_string(),
+                },
+                300,
+                "example.com",
+                CreateOptions::default(),
+            )
+            .await;
+
+        assert!(
+            matches!(&result, Err(Error::Api(err)) if err.contains("TXT record instead")),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn set_ttl_keeps_content() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 7, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 3600 },
+                ])),
+            ),
+            MockRoute::new(
+                "PUT",
+                format!("{INFOMANIAK_RECORDS}/7"),
+                infomaniak_success(json!(true)),
+            ),
+        ])
+        .await;
+
+        infomaniak(&server)
+            .set_ttl("www.example.com", DnsRecordType::A, 60, "example.com")
+            .await
+            .unwrap();
+
+        let updates = server.requests_to("PUT", INFOMANIAK_RECORDS);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].json(),
+            json!({ "type": "A", "source": "www", "target": "192.0.2.1", "ttl": 60 })
+        );
+    }
+
+    #[test]
+    fn empty_credentials_are_rejected() {
+        for secret in ["", "  \n"] {
+            assert!(matches!(
+                DnsUpdater::new_cloudflare(secret, None::<String>, None),
+                Err(Error::Config(_))
+            ));
+            assert!(matches!(
+                DnsUpdater::new_cloudflare("token", Some(secret), None),
+                Err(Error::Config(_))
+            ));
+            assert!(matches!(
+                DnsUpdater::new_infomaniak(secret, None),
+                Err(Error::Config(_))
+            ));
+            assert!(matches!(
+                DnsUpdater::new_rfc2136_tsig(
+                    "udp://127.0.0.1:53",
+                    secret,
+                    b"secret".to_vec(),
+                    TsigAlgorithm::HmacSha256,
+                ),
+                Err(Error::Config(_))
+            ));
         }
+
+        // TSIG keys are binary, so only an empty key is rejected.
+        assert!(matches!(
+            DnsUpdater::new_rfc2136_tsig(
+                "udp://127.0.0.1:53",
+                "key",
+                Vec::new(),
+                TsigAlgorithm::HmacSha256,
+            ),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn zone_updater_resolves_zone_once() {
+        let server = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+        let zone = cloudflare(&server).zone("example.com");
+
+        for name in ["a.example.com", "b.example.com", "c.example.com"] {
+            zone.create(
+                name,
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(server.requests_to("GET", "/client/v4/zones?").len(), 1);
+        assert_eq!(
+            server
+                .requests_to("POST", "/client/v4/zones/z1/dns_records")
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn caa_record_is_created_when_missing() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 1, "source": "@", "type": "A", "target": "192.0.2.1", "ttl": 3600 },
+                ])),
+            ),
+            MockRoute::new("POST", INFOMANIAK_RECORDS, infomaniak_success(json!(2))),
+        ])
+        .await;
+
+        let status = infomaniak(&server)
+            .ensure_caa_allows("example.com", "letsencrypt.org")
+            .await
+            .unwrap();
+        assert!(status.allowed);
+        assert!(status.created);
+
+        let creates = server.requests_to("POST", INFOMANIAK_RECORDS);
+        assert_eq!(creates.len(), 1);
+        assert_eq!(
+            creates[0].json(),
+            json!({ "type": "CAA", "source": "@", "target": "0 issue \"letsencrypt.org\"", "ttl": 3600 })
+        );
+    }
+
+    #[tokio::test]
+    async fn existing_caa_records_are_checked() {
+        let server = MockServer::routes(vec![MockRoute::new(
+            "GET",
+            INFOMANIAK_RECORDS,
+            infomaniak_success(json!([
+                { "id": 1, "source": "@", "type": "CAA", "target": "0 issue \"digicert.com\"", "ttl": 3600 },
+                { "id": 2, "source": "@", "type": "CAA", "target": "0 iodef \"mailto:ca@example.com\"", "ttl": 3600 },
+            ])),
+        )])
+        .await;
+        let updater = infomaniak(&server);
+
+        let status = updater
+            .ensure_caa_allows("example.com", "letsencrypt.org")
+            .await
+            .unwrap();
+        assert!(!status.allowed);
+        assert!(!status.created);
+        assert_eq!(status.records.len(), 2);
+
+        let status = updater
+            .ensure_caa_allows("example.com", "DigiCert.com")
+            .await
+            .unwrap();
+        assert!(status.allowed);
+        assert!(server.requests_to("POST", INFOMANIAK_RECORDS).is_empty());
+    }
+
+    #[tokio::test]
+    async fn zone_export_is_imported_into_another_provider() {
+        let source = MockServer::routes(vec![MockRoute::new(
+            "GET",
+            INFOMANIAK_RECORDS,
+            infomaniak_success(json!([
+                { "id": 1, "source": "@", "type": "MX", "target": "10 mail.example.com", "ttl": 3600 },
+                { "id": 2, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+            ])),
+        )])
+        .await;
+        let target = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+
+        let records = infomaniak(&source)
+            .export_zone("example.com")
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        let results = cloudflare(&target)
+            .import_zone("example.com", records)
+            .await;
+        assert!(results.iter().all(Result::is_ok), "{results:?}");
+
+        let creates = target
+            .requests_to("POST", "/client/v4/zones/z1/dns_records")
+            .into_iter()
+            .map(|request| {
+                let body = request.json();
+                (
+                    body["name"].clone(),
+                    body["type"].clone(),
+                    body["ttl"].clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            creates,
+            [
+                (json!("example.com"), json!("MX"), json!(3600)),
+                (json!("www.example.com"), json!("A"), json!(300)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn str_variants() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 3, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("POST", INFOMANIAK_RECORDS, infomaniak_success(json!(3))),
+            MockRoute::new("PUT", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+            MockRoute::new(
+                "DELETE",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!(true)),
+            ),
+        ])
+        .await;
+        let updater = Box::new(infomaniak(&server));
+        let name = String::from("www.example.com");
+        let record = DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+
+        updater
+            .create_str(&name, record.clone(), Ttl::Seconds(300), "example.com")
+            .await
+            .unwrap();
+        updater
+            .update_str(&name, record, Ttl::Seconds(600), "example.com")
+            .await
+            .unwrap();
+        updater.delete_str(&name, "example.com").await.unwrap();
+
+        let methods = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.method != "GET")
+            .map(|request| (request.method, request.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            methods,
+            [
+                ("POST".to_string(), INFOMANIAK_RECORDS.to_string()),
+                ("PUT".to_string(), format!("{INFOMANIAK_RECORDS}/3")),
+                ("DELETE".to_string(), format!("{INFOMANIAK_RECORDS}/3")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn import_can_be_cancelled() {
+        let server = MockServer::routes_with_delay(
+            vec![MockRoute::new(
+                "POST",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!(1)),
+            )],
+            Duration::from_secs(5),
+        )
+        .await;
+        let records = (0..3)
+            .map(|pos| ExportedRecord {
+                name: format!("host{pos}.example.com"),
+                record: DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                ttl: 300,
+            })
+            .collect();
+
+        let started = Instant::now();
+        let result = infomaniak(&server)
+            .import_zone_cancellable(
+                "example.com",
+                records,
+                tokio::time::sleep(Duration::from_millis(50)),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Cancelled)), "{result:?}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn cname_redirects_the_write_target() {
+        mock_dns(
+            &[("_acme-challenge.example.com", "_acme-challenge.example.net")],
+            &["example.com", "example.net"],
+        )
+        .await;
+        let server = MockServer::routes(vec![MockRoute::new(
+            "POST",
+            "/2/zones/example.net/records",
+            infomaniak_success(json!(1)),
+        )])
+        .await;
+
+        infomaniak(&server)
+            .create_following_cname(
+                "_acme-challenge.example.com",
+                DnsRecord::TXT {
+                    content: "token".to_string(),
+                },
+                60,
+            )
+            .await
+            .unwrap();
+
+        let creates = server.requests_to("POST", "/2/zones/example.net/records");
+        assert_eq!(creates.len(), 1);
+        assert_eq!(creates[0].json()["source"], json!("_acme-challenge"));
+    }
+
+    #[tokio::test]
+    async fn records_are_streamed_across_pages() {
+        fn page(result: serde_json::Value, page: u32, total_pages: u32) -> MockResponse {
+            MockResponse::Json(
+                200,
+                json!({
+                    "success": true,
+                    "errors": [],
+                    "result": result,
+                    "result_info": { "page": page, "total_pages": total_pages },
+                }),
+            )
+        }
+
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones?page=1&",
+                page(json!([{ "id": "z1", "name": "example.com" }]), 1, 2),
+            ),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones?page=2&",
+                page(json!([{ "id": "z2", "name": "example.org" }]), 2, 2),
+            ),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones?name=example.com",
+                cloudflare_ok(json!([{ "id": "z1", "name": "example.com" }])),
+            ),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones?name=example.org",
+                cloudflare_ok(json!([{ "id": "z2", "name": "example.org" }])),
+            ),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones/z1/dns_records?page=1&",
+                page(
+                    json!([{ "id": "r1", "name": "www.example.com", "type": "A", "content": "192.0.2.1", "ttl": 300 }]),
+                    1,
+                    2,
+                ),
+            ),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones/z1/dns_records?page=2&",
+                page(
+                    json!([{ "id": "r2", "name": "example.com", "type": "MX", "content": "mail.example.com", "priority": 10, "ttl": 3600 }]),
+                    2,
+                    2,
+                ),
+            ),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones/z2/dns_records?page=1&",
+                page(
+                    json!([{ "id": "r3", "name": "example.org", "type": "TXT", "content": "hello", "ttl": 60 }]),
+                    1,
+                    1,
+                ),
+            ),
+        ])
+        .await;
+        let updater = cloudflare(&server);
+
+        let records = updater
+            .stream_records()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .map(|(zone, name, record, ttl)| (
+                    zone.as_str(),
+                    name.as_str(),
+                    record.as_type(),
+                    *ttl
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("example.com", "www.example.com", DnsRecordType::A, 300),
+                ("example.com", "example.com", DnsRecordType::MX, 3600),
+                ("example.org", "example.org", DnsRecordType::TXT, 60),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn public_ip_is_upserted() {
+        let echo = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                "/trace",
+                MockResponse::Status(200, "fl=1\nip=192.0.2.7\nts=1\n"),
+            ),
+            MockRoute::new("GET", "/ip", MockResponse::Status(200, "192.0.2.1\n")),
+        ])
+        .await;
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 4, "source": "home", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("PUT", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+        ])
+        .await;
+        let updater = infomaniak(&server);
+
+        // Already up to date, nothing is sent.
+        let ip = updater
+            .update_to_public_ip_using(
+                "home.example.com",
+                "example.com",
+                300,
+                format!("{}/ip", echo.url),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ip, "192.0.2.1".parse::<std::net::IpAddr>().unwrap());
+        assert!(server.requests_to("PUT", INFOMANIAK_RECORDS).is_empty());
+
+        let ip = updater
+            .update_to_public_ip_using(
+                "home.example.com",
+                "example.com",
+                300,
+                format!("{}/trace", echo.url),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ip, "192.0.2.7".parse::<std::net::IpAddr>().unwrap());
+        let updates = server.requests_to("PUT", INFOMANIAK_RECORDS);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].path, format!("{INFOMANIAK_RECORDS}/4"));
+        assert_eq!(updates[0].json()["target"], json!("192.0.2.7"));
+    }
+
+    #[tokio::test]
+    async fn cname_is_created_proxied() {
+        let server = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+
+        cloudflare(&server)
+            .create_cname_proxied("app.example.com", "tunnel.cfargotunnel.com", "example.com")
+            .await
+            .unwrap();
+
+        let creates = server.requests_to("POST", "/client/v4/zones/z1/dns_records");
+        assert_eq!(creates.len(), 1);
+        let body = creates[0].json();
+        assert_eq!(body["type"], json!("CNAME"));
+        assert_eq!(body["content"], json!("tunnel.cfargotunnel.com"));
+        assert_eq!(body["proxied"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn create_if_absent_only_creates_missing_records() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 5, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("POST", INFOMANIAK_RECORDS, infomaniak_success(json!(6))),
+        ])
+        .await;
+        let updater = infomaniak(&server);
+        let a = |content: &str| DnsRecord::A {
+            content: content.parse().unwrap(),
+        };
+
+        assert!(!updater
+            .create_if_absent("www.example.com", a("192.0.2.1"), 300, "example.com")
+            .await
+            .unwrap());
+        assert!(server.requests_to("POST", INFOMANIAK_RECORDS).is_empty());
+
+        assert!(updater
+            .create_if_absent("www.example.com", a("192.0.2.2"), 300, "example.com")
+            .await
+            .unwrap());
+        let creates = server.requests_to("POST", INFOMANIAK_RECORDS);
+        assert_eq!(creates.len(), 1);
+        assert_eq!(creates[0].json()["target"], json!("192.0.2.2"));
+    }
+
+    #[test]
+    fn canonical_names() {
+        for name in ["WWW.Example.COM", "www.example.com.", "www.EXAMPLE.com."] {
+            assert_eq!(canonical_name(name), "www.example.com");
+        }
+        assert_eq!(
+            strip_origin_from_name("WWW.Example.COM.", "example.com"),
+            "www"
+        );
+        assert_eq!(strip_origin_from_name("Example.COM", "example.com."), "@");
+    }
+
+    #[test]
+    fn tsig_algorithm_names() {
+        for name in ["hmac-sha256", "HMAC-SHA256", "hmac_sha256", " Hmac_Sha256 "] {
+            assert!(
+                matches!(name.parse(), Ok(TsigAlgorithm::HmacSha256)),
+                "{name}"
+            );
+        }
+        assert!(matches!(
+            "HMAC_SHA512_256".parse(),
+            Ok(TsigAlgorithm::HmacSha512_256)
+        ));
+        for name in TsigAlgorithm::all() {
+            assert!(name.parse::<TsigAlgorithm>().is_ok(), "{name}");
+        }
+
+        let err = "hmac-sha3".parse::<TsigAlgorithm>().err().unwrap();
+        assert!(
+            matches!(&err, Error::Parse(err) if err.contains("hmac-sha256, hmac-sha256-128")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn reverse_names() {
+        assert_eq!(
+            reverse_dns_name("192.0.2.10".parse().unwrap()),
+            "10.2.0.192.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_dns_name("2001:db8::567:89ab".parse().unwrap()),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_cache_lookups_share_one_fetch() {
+        let cache = ApiCacheManager::<String>::default();
+        let fetches = AtomicUsize::new(0);
+
+        let results = join_all((0..8).map(|_| {
+            cache.get_or_update("example.com", || async {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Ok("z1".to_string())
+            })
+        }))
+        .await;
+
+        assert!(results
+            .iter()
+            .all(|result| matches!(result.as_deref(), Ok("z1"))));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn metrics_are_reported_once_per_create() {
+        let server = MockServer::routes_with_delay(
+            vec![
+                cloudflare_zone(),
+                MockRoute::new(
+                    "POST",
+                    "/client/v4/zones/z1/dns_records",
+                    cloudflare_ok(json!({})),
+                ),
+            ],
+            Duration::from_millis(20),
+        )
+        .await;
+        let metrics = Arc::new(Mutex::new(Vec::<OperationMetric>::new()));
+        let collected = metrics.clone();
+        let updater = cloudflare(&server).with_metrics(Arc::new(move |metric| {
+            collected.lock().unwrap().push(metric)
+        }));
+        let record = DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+
+        updater
+            .create("www.example.com", record.clone(), 300, "example.com")
+            .await
+            .unwrap();
+        updater
+            .create_with_options(
+                "api.example.com",
+                record,
+                300,
+                "example.com",
+                CreateOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        let metrics = metrics.lock().unwrap();
+        assert_eq!(metrics.len(), 2);
+        for metric in metrics.iter() {
+            assert_eq!(metric.provider, "cloudflare");
+            assert_eq!(metric.operation, Operation::Create);
+            assert!(metric.success);
+            assert!(metric.duration >= Duration::from_millis(20), "{metric:?}");
+            assert!(metric.duration < Duration::from_secs(5), "{metric:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn update_if_changed_skips_matching_records() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 8, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("PUT", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+        ])
+        .await;
+        let updater = infomaniak(&server);
+        let a = |content: &str| DnsRecord::A {
+            content: content.parse().unwrap(),
+        };
+
+        assert!(!updater
+            .update_if_changed("www.example.com", a("192.0.2.1"), 300, "example.com")
+            .await
+            .unwrap());
+        assert!(server.requests_to("PUT", INFOMANIAK_RECORDS).is_empty());
+
+        assert!(updater
+            .update_if_changed("www.example.com", a("192.0.2.1"), 600, "example.com")
+            .await
+            .unwrap());
+        assert!(updater
+            .update_if_changed("www.example.com", a("192.0.2.2"), 300, "example.com")
+            .await
+            .unwrap());
+        assert_eq!(server.requests_to("PUT", INFOMANIAK_RECORDS).len(), 2);
+    }
+
+    #[test]
+    fn timeouts_default_and_override() {
+        let timeout = Duration::from_secs(5);
+        for (updater, expected) in [
+            (
+                DnsUpdater::new_cloudflare("token", None::<String>, None).unwrap(),
+                crate::http::DEFAULT_TIMEOUT,
+            ),
+            (
+                DnsUpdater::new_cloudflare("token", None::<String>, Some(timeout)).unwrap(),
+                timeout,
+            ),
+            (
+                DnsUpdater::new_infomaniak("token", None).unwrap(),
+                crate::http::DEFAULT_TIMEOUT,
+            ),
+            (
+                DnsUpdater::new_infomaniak("token", Some(timeout)).unwrap(),
+                timeout,
+            ),
+        ] {
+            assert_eq!(updater.timeout(), expected);
+        }
+
+        let builder = crate::http::HttpClientBuilder::default().with_timeout(Some(timeout));
+        assert_eq!(builder.timeout(), timeout);
+        assert_eq!(
+            builder.with_timeout(None).timeout(),
+            crate::http::DEFAULT_TIMEOUT
+        );
+    }
+
+    #[tokio::test]
+    async fn cleanup_deletes_matching_records_only() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 1, "source": "_acme-challenge", "type": "TXT", "target": "\"a\"", "ttl": 60 },
+                    { "id": 2, "source": "_acme-challenge.www", "type": "TXT", "target": "\"b\"", "ttl": 60 },
+                    { "id": 3, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                    { "id": 4, "source": "@", "type": "TXT", "target": "\"v=spf1 -all\"", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("DELETE", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+        ])
+        .await;
+
+        let deleted = infomaniak(&server)
+            .cleanup("example.com", |name, record| {
+                name.starts_with("_acme-challenge.") && record.as_type() == DnsRecordType::TXT
+            })
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(
+            server
+                .requests_to("DELETE", INFOMANIAK_RECORDS)
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [
+                format!("{INFOMANIAK_RECORDS}/1"),
+                format!("{INFOMANIAK_RECORDS}/2"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn apex_and_www_are_created() {
+        let server = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+        let updater = cloudflare(&server);
+
+        updater
+            .create_apex_and_www("example.com", "192.0.2.1", 300)
+            .await
+            .unwrap();
+        updater
+            .create_apex_and_www("example.com", "site.example.net", 300)
+            .await
+            .unwrap();
+
+        let creates = server
+            .requests_to("POST", "/client/v4/zones/z1/dns_records")
+            .into_iter()
+            .map(|request| {
+                let body = request.json();
+                (body["name"].clone(), body["type"].clone())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            creates,
+            [
+                (json!("example.com"), json!("A")),
+                (json!("www.example.com"), json!("A")),
+                (json!("example.com"), json!("CNAME")),
+                (json!("www.example.com"), json!("CNAME")),
+            ]
+        );
+
+        let server = MockServer::routes(vec![]).await;
+        assert!(matches!(
+            infomaniak(&server)
+                .create_apex_and_www("example.com", "site.example.net", 300)
+                .await,
+            Err(Error::Api(_))
+        ));
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn automatic_ttls_map_to_provider_values() {
+        let server = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+        let updater = cloudflare(&server);
+
+        assert_eq!(updater.ttl_seconds(Ttl::Seconds(300)), 300);
+        assert_eq!(updater.ttl_seconds(Ttl::ZoneDefault), 1);
+        assert_eq!(rfc2136().ttl_seconds(Ttl::Auto), DEFAULT_TTL);
+        assert_eq!(rfc2136().ttl_seconds(300.into()), 300);
+
+        updater
+            .create(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                Ttl::Auto,
+                "example.com",
+            )
+            .await
+            .unwrap();
+        let creates = server.requests_to("POST", "/client/v4/zones/z1/dns_records");
+        assert_eq!(creates[0].json()["ttl"], json!(1));
+    }
+
+    #[tokio::test]
+    async fn written_records_are_read_back() {
+        let server = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!([
+                    { "id": "r1", "name": "www.example.com", "type": "A", "content": "192.0.2.1", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+        let updater = cloudflare(&server);
+        let a = |content: &str| DnsRecord::A {
+            content: content.parse().unwrap(),
+        };
+
+        assert!(matches!(
+            updater
+                .create_and_verify("www.example.com", a("192.0.2.2"), 300, "example.com")
+                .await,
+            Err(Error::Api(_))
+        ));
+        updater
+            .create_and_verify("www.example.com", a("192.0.2.1"), 300, "example.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            server
+                .requests_to("POST", "/client/v4/zones/z1/dns_records")
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_all_of_type_keeps_other_types() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 1, "source": "@", "type": "TXT", "target": "\"v=spf1 -all\"", "ttl": 300 },
+                    { "id": 2, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                    { "id": 3, "source": "_dmarc", "type": "TXT", "target": "\"v=DMARC1; p=none\"", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("DELETE", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+        ])
+        .await;
+
+        let results = infomaniak(&server)
+            .delete_all_of_type("example.com", DnsRecordType::TXT)
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(name, result)| (name.as_str(), result.is_ok()))
+                .collect::<Vec<_>>(),
+            [("example.com", true), ("_dmarc.example.com", true)]
+        );
+        assert_eq!(
+            server
+                .requests_to("DELETE", INFOMANIAK_RECORDS)
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [
+                format!("{INFOMANIAK_RECORDS}/1"),
+                format!("{INFOMANIAK_RECORDS}/3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn configuration_errors_are_distinct_from_parse_errors() {
+        let Err(err) = DnsUpdater::new_rfc2136_tsig(
+            "udp://dns.example.com",
+            "key",
+            b"secret".to_vec(),
+            TsigAlgorithm::HmacSha256,
+        ) else {
+            unreachable!()
+        };
+        assert!(matches!(err, Error::Config(_)), "{err:?}");
+        assert_eq!(err.to_string(), "Configuration error: Invalid address");
+
+        assert!(matches!(
+            DnsRecord::MX {
+                content: "192.0.2.1".to_string(),
+                priority: 10,
+            }
+            .validate(),
+            Err(Error::Parse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn relative_and_qualified_names_match() {
+        let server = MockServer::routes(vec![MockRoute::new(
+            "POST",
+            INFOMANIAK_RECORDS,
+            infomaniak_success(json!(1)),
+        )])
+        .await;
+        let updater = infomaniak(&server);
+        let a = || DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+
+        updater
+            .create("www.example.com", a(), 300, "example.com")
+            .await
+            .unwrap();
+        updater
+            .create_relative("www", a(), 300, "example.com")
+            .await
+            .unwrap();
+        updater
+            .create("example.com", a(), 300, "example.com")
+            .await
+            .unwrap();
+        updater
+            .create_relative("@", a(), 300, "example.com.")
+            .await
+            .unwrap();
+        // A subname that looks like the origin is still relative to it.
+        updater
+            .create_relative("example.com", a(), 300, "example.com")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server
+                .requests_to("POST", INFOMANIAK_RECORDS)
+                .into_iter()
+                .map(|request| request.json()["source"].clone())
+                .collect::<Vec<_>>(),
+            [
+                json!("www"),
+                json!("www"),
+                json!("@"),
+                json!("@"),
+                json!("example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_with_dots_are_absolute() {
+        assert_eq!(
+            Target::from("mail.other-domain.com"),
+            Target::Absolute("mail.other-domain.com".to_string())
+        );
+        assert_eq!(
+            Target::from("mail.other-domain.com.").to_string(),
+            "mail.other-domain.com."
+        );
+        assert_eq!(Target::from("mail"), Target::Relative("mail".to_string()));
+        assert_eq!(Target::from("mail").to_string(), "mail");
+    }
+
+    #[test]
+    fn owned_names_reuse_their_buffer() {
+        let mut name = String::with_capacity(16);
+        name.push_str("example.com");
+        let buffer = name.as_ptr();
+        let fqdn = name.into_fqdn();
+        assert_eq!(fqdn, "example.com.");
+        assert_eq!(fqdn.as_ptr(), buffer);
+
+        let name = fqdn.into_owned();
+        let buffer = name.as_ptr();
+        let name = name.into_name();
+        assert_eq!(name, "example.com");
+        assert_eq!(name.as_ptr(), buffer);
+
+        assert!(matches!("example.com.".into_fqdn(), Cow::Borrowed(_)));
+        assert!(matches!("example.com".into_name(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn planned_requests_are_described_without_credentials() {
+        let updater = DnsUpdater::new_infomaniak("token", None).unwrap();
+        let create = Change::Create {
+            name: "www.example.com".to_string(),
+            record: DnsRecord::A {
+                content: "192.0.2.1".parse().unwrap(),
+            },
+            ttl: 300,
+        };
+
+        let request = updater.describe_request(&create, "example.com").unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            request.url,
+            "https://api.infomaniak.com/2/zones/example.com/records"
+        );
+        assert!(request
+            .headers
+            .iter()
+            .any(|(name, value)| name == "authorization" && value == "<redacted>"));
+        assert!(request
+            .headers
+            .iter()
+            .all(|(_, value)| !value.contains("token")));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(request.body.as_deref().unwrap()).unwrap(),
+            json!({ "type": "A", "source": "www", "target": "192.0.2.1", "ttl": 300 })
+        );
+
+        let delete = Change::Delete {
+            name: "www.example.com".to_string(),
+        };
+        let request = updater.describe_request(&delete, "example.com").unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.body, None);
+
+        let request = DnsUpdater::new_cloudflare("token", None::<String>, None)
+            .unwrap()
+            .describe_request(&create, "example.com")
+            .unwrap();
+        assert_eq!(
+            request.url,
+            "https://api.cloudflare.com/client/v4/zones?name=example.com"
+        );
+
+        assert!(rfc2136().describe_request(&create, "example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn multi_updater_reports_partial_failures() {
+        let primary = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "POST",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+        let secondary = MockServer::routes(vec![MockRoute::new(
+            "POST",
+            INFOMANIAK_RECORDS,
+            MockResponse::Status(500, "down"),
+        )])
+        .await;
+        let updater =
+            DnsUpdater::new_multi(vec![cloudflare(&primary), infomaniak(&secondary)]).unwrap();
+
+        let result = updater
+            .create(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+                "example.com",
+            )
+            .await;
+        assert!(
+            matches!(&result, Err(Error::Api(err)) if err.starts_with("1 of 2 updaters failed: infomaniak: ")),
+            "{result:?}"
+        );
+        assert_eq!(
+            primary
+                .requests_to("POST", "/client/v4/zones/z1/dns_records")
+                .len(),
+            1
+        );
+        assert!(!secondary.requests_to("POST", INFOMANIAK_RECORDS).is_empty());
+
+        assert!(matches!(
+            DnsUpdater::new_multi(Vec::new()),
+            Err(Error::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn cloudflare_automatic_ttl_is_read_back_as_auto() {
+        let server = MockServer::routes(vec![
+            cloudflare_zone(),
+            MockRoute::new(
+                "GET",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!([
+                    { "id": "r1", "name": "www.example.com", "type": "A", "content": "192.0.2.1", "ttl": 1 },
+                ])),
+            ),
+            MockRoute::new(
+                "PATCH",
+                "/client/v4/zones/z1/dns_records",
+                cloudflare_ok(json!({})),
+            ),
+        ])
+        .await;
+        let updater = cloudflare(&server);
+
+        assert_eq!(updater.ttl_from_seconds(1), Ttl::Auto);
+        assert_eq!(updater.ttl_from_seconds(300), Ttl::Seconds(300));
+        assert_eq!(rfc2136().ttl_from_seconds(1), Ttl::Seconds(1));
+
+        assert!(!updater
+            .update_if_changed(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                Ttl::Auto,
+                "example.com",
+            )
+            .await
+            .unwrap());
+        assert!(server
+            .requests_to("PATCH", "/client/v4/zones/z1/dns_records")
+            .is_empty());
+
+        assert_eq!(
+            updater
+                .plan(
+                    "example.com",
+                    vec![(
+                        "www.example.com".to_string(),
+                        DnsRecord::A {
+                            content: "192.0.2.1".parse().unwrap(),
+                        },
+                        Ttl::Auto,
+                    )],
+                )
+                .await
+                .unwrap(),
+            []
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_root_certificates_are_trusted() {
+        let server = MockServer::tls(vec![cloudflare_zone()]).await;
+
+        assert!(cloudflare(&server).list_zones().await.is_err());
+        assert_eq!(
+            cloudflare(&server)
+                .with_root_certificate(TEST_CA)
+                .unwrap()
+                .list_zones()
+                .await
+                .unwrap(),
+            vec!["example.com".to_string()]
+        );
+
+        for pem in [
+            "",
+            "not a certificate",
+            "-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydGlmaWNhdGU=\n-----END CERTIFICATE-----\n",
+        ] {
+            let Err(err) = cloudflare(&server).with_root_certificate(pem) else {
+                unreachable!()
+            };
+            assert!(matches!(err, Error::Config(_)), "{err:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn plan_creates_updates_and_deletes() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                INFOMANIAK_RECORDS,
+                infomaniak_success(json!([
+                    { "id": 1, "source": "@", "type": "NS", "target": "ns1.example.net", "ttl": 3600 },
+                    { "id": 2, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 300 },
+                    { "id": 3, "source": "old", "type": "TXT", "target": "stale", "ttl": 300 },
+                    { "id": 4, "source": "mail", "type": "A", "target": "192.0.2.3", "ttl": 300 },
+                ])),
+            ),
+            MockRoute::new("POST", INFOMANIAK_RECORDS, infomaniak_success(json!(5))),
+            MockRoute::new("PUT", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+            MockRoute::new("DELETE", INFOMANIAK_RECORDS, infomaniak_success(json!(true))),
+        ])
+        .await;
+        let updater = infomaniak(&server);
+        let a = |content: &str| DnsRecord::A {
+            content: content.parse().unwrap(),
+        };
+
+        let plan = updater
+            .plan(
+                "example.com",
+                vec![
+                    ("www.example.com".to_string(), a("192.0.2.2"), 300),
+                    ("mail.example.com".to_string(), a("192.0.2.3"), 300),
+                    ("ftp.example.com".to_string(), a("192.0.2.4"), 300),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            plan,
+            [
+                PlannedChange::Delete {
+                    name: "old.example.com".to_string(),
+                    record: DnsRecord::TXT {
+                        content: "stale".to_string(),
+                    },
+                },
+                PlannedChange::Update {
+                    name: "www.example.com".to_string(),
+                    record: a("192.0.2.2"),
+                    ttl: Ttl::Seconds(300),
+                },
+                PlannedChange::Create {
+                    name: "ftp.example.com".to_string(),
+                    record: a("192.0.2.4"),
+                    ttl: Ttl::Seconds(300),
+                },
+            ]
+        );
+
+        updater.apply("example.com", plan).await.unwrap();
+        assert_eq!(
+            server
+                .requests_to("DELETE", INFOMANIAK_RECORDS)
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [format!("{INFOMANIAK_RECORDS}/3")]
+        );
+        assert_eq!(
+            server
+                .requests_to("PUT", INFOMANIAK_RECORDS)
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [format!("{INFOMANIAK_RECORDS}/2")]
+        );
+        assert_eq!(server.requests_to("POST", INFOMANIAK_RECORDS).len(), 1);
     }
 }
@@ -10,6 +10,7 @@
  */
 
 use std::{
+    borrow::Cow,
     net::{Ipv4Addr, Ipv6Addr},
     time::Duration,
 };
@@ -17,11 +18,25 @@ use std::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{http::HttpClientBuilder, DnsRecord, Error, IntoFqdn};
+use crate::{
+    canonical_name,
+    http::{HttpClient, HttpClientBuilder, PlannedRequest, RateLimitInfo},
+    ApiCacheManager, Batch, Capabilities, CreateOptions, DnsRecord, DnsRecordType, DnssecKey,
+    Error, IntoFqdn, MetricsCallback, Ttl,
+};
+
+use super::require_credential;
+
+/// Lowest TTL accepted by Cloudflare, other than 1 for "automatic".
+const MIN_TTL: u32 = 60;
 
 #[derive(Clone)]
 pub struct CloudflareProvider {
     client: HttpClientBuilder,
+    zone_ids: ApiCacheManager<String>,
+    metrics: Option<MetricsCallback>,
+    clamp_ttl: bool,
+    account_id: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -30,9 +45,33 @@ pub struct IdMap {
     pub name: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct ZoneDetails {
+    #[serde(default)]
+    name_servers: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TypeMap {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DnsRecordEntry {
+    pub id: String,
+    pub name: String,
+    pub ttl: u32,
+    #[serde(flatten)]
+    pub content: DnsContent,
+}
+
 #[derive(Serialize, Debug)]
 pub struct Query {
     name: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    record_type: Option<&'static str>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -43,6 +82,8 @@ pub struct CreateDnsRecordParams<'a> {
     pub priority: Option<u16>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub name: &'a str,
     #[serde(flatten)]
     pub content: DnsContent,
@@ -53,7 +94,11 @@ pub struct UpdateDnsRecordParams<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ttl: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub name: &'a str,
     #[serde(flatten)]
     pub content: DnsContent,
@@ -63,13 +108,126 @@ pub struct UpdateDnsRecordParams<'a> {
 #[serde(tag = "type")]
 #[allow(clippy::upper_case_acronyms)]
 pub enum DnsContent {
-    A { content: Ipv4Addr },
-    AAAA { content: Ipv6Addr },
-    CNAME { content: String },
-    NS { content: String },
-    MX { content: String, priority: u16 },
-    TXT { content: String },
-    SRV { content: String },
+    A {
+        content: Ipv4Addr,
+    },
+    AAAA {
+        content: Ipv6Addr,
+    },
+    CNAME {
+        content: String,
+    },
+    NS {
+        content: String,
+    },
+    MX {
+        content: String,
+        priority: u16,
+    },
+    TXT {
+        content: String,
+    },
+    SRV {
+        content: String,
+        /// Read from the record's top-level `priority`, requests send it in
+        /// their own `priority` field.
+        #[serde(default, skip_serializing)]
+        priority: u16,
+    },
+    CAA {
+        data: CaaData,
+    },
+    CERT {
+        data: CertData,
+    },
+    SMIMEA {
+        data: SmimeaData,
+    },
+    OPENPGPKEY {
+        content: String,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CaaData {
+    pub flags: u8,
+    pub tag: String,
+    pub value: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CertData {
+    #[serde(rename = "type")]
+    pub cert_type: u16,
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub certificate: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SmimeaData {
+    pub usage: u8,
+    pub selector: u8,
+    pub matching_type: u8,
+    pub certificate: String,
+}
+
+#[derive(Serialize, Debug)]
+struct RenameParams<'a> {
+    name: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchParams<'a> {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    deletes: Vec<BatchDelete>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    patches: Vec<BatchPatch<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    posts: Vec<CreateDnsRecordParams<'a>>,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchDelete {
+    id: String,
+}
+
+#[derive(Serialize, Debug)]
+struct BatchPatch<'a> {
+    id: String,
+    #[serde(flatten)]
+    params: UpdateDnsRecordParams<'a>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct BatchResult {
+    #[serde(default)]
+    deletes: Vec<Value>,
+    #[serde(default)]
+    patches: Vec<Value>,
+    #[serde(default)]
+    posts: Vec<Value>,
+}
+
+#[derive(Serialize, Debug)]
+struct TagQuery<'a> {
+    tag: &'a str,
+    page: u32,
+    per_page: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DnssecParams {
+    pub status: &'static str,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DnssecResult {
+    pub status: String,
+    pub key_tag: Option<u16>,
+    pub algorithm: Option<String>,
+    pub digest_type: Option<String>,
+    pub digest: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -77,6 +235,14 @@ struct ApiResult<T> {
     errors: Vec<ApiError>,
     success: bool,
     result: T,
+    #[serde(default)]
+    result_info: Option<ResultInfo>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ResultInfo {
+    pub page: u32,
+    pub total_pages: u32,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -86,86 +252,397 @@ pub struct ApiError {
 }
 
 impl CloudflareProvider {
+    pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+        record_types: Cow::Borrowed(&[
+            DnsRecordType::A,
+            DnsRecordType::AAAA,
+            DnsRecordType::CNAME,
+            DnsRecordType::NS,
+            DnsRecordType::MX,
+            DnsRecordType::TXT,
+            DnsRecordType::SRV,
+            DnsRecordType::CAA,
+            DnsRecordType::CERT,
+            DnsRecordType::SMIMEA,
+            DnsRecordType::OPENPGPKEY,
+        ]),
+        supports_list: true,
+        supports_multi_value: true,
+    };
+
     pub(crate) fn new(
         secret: impl AsRef<str>,
         email: Option<impl AsRef<str>>,
         timeout: Option<Duration>,
     ) -> crate::Result<Self> {
+        let secret = require_credential(secret.as_ref())?;
         let client = if let Some(email) = email {
             HttpClientBuilder::default()
-                .with_header("X-Auth-Email", email.as_ref())
-                .with_header("X-Auth-Key", secret.as_ref())
+                .with_header("X-Auth-Email", require_credential(email.as_ref())?)
+                .with_header("X-Auth-Key", secret)
         } else {
-            HttpClientBuilder::default()
-                .with_header("Authorization", format!("Bearer {}", secret.as_ref()))
+            HttpClientBuilder::default().with_header("Authorization", format!("Bearer {secret}"))
         }
         .with_timeout(timeout);
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            zone_ids: ApiCacheManager::default(),
+            metrics: None,
+            clamp_ttl: false,
+            account_id: None,
+        })
+    }
+
+    pub(crate) fn map_client(
+        mut self,
+        f: impl FnOnce(HttpClientBuilder) -> HttpClientBuilder,
+    ) -> Self {
+        self.client = f(self.client);
+        self
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.client.timeout()
+    }
+
+    pub(crate) fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        self.client.last_rate_limit()
+    }
+
+    pub(crate) fn with_metrics(mut self, metrics: MetricsCallback) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&MetricsCallback> {
+        self.metrics.as_ref()
+    }
+
+    pub(crate) fn with_clamp_ttl(mut self, clamp_ttl: bool) -> Self {
+        self.clamp_ttl = clamp_ttl;
+        self
+    }
+
+    pub(crate) fn with_account_id(mut self, account_id: String) -> Self {
+        self.account_id = Some(account_id);
+        self
+    }
+
+    /// Raise `ttl` to Cloudflare's minimum when clamping is enabled. A TTL of 1
+    /// means "automatic" and is left alone.
+    fn clamp_ttl(&self, ttl: u32) -> u32 {
+        if self.clamp_ttl && ttl != 1 && ttl < MIN_TTL {
+            tracing::warn!("TTL {ttl} is below the Cloudflare minimum, using {MIN_TTL}");
+            MIN_TTL
+        } else {
+            ttl
+        }
+    }
+
+    /// Cloudflare's TTL for `ttl`, where 1 means "automatic".
+    fn ttl_seconds(&self, ttl: Ttl) -> u32 {
+        match ttl {
+            Ttl::Seconds(seconds) => self.clamp_ttl(seconds),
+            Ttl::Auto | Ttl::ZoneDefault => 1,
+        }
+    }
+
+    fn zone_lookup(&self, origin: &str) -> HttpClient {
+        let mut query = Query::name(origin).serialize();
+        if let Some(account_id) = &self.account_id {
+            query.push('&');
+            query.push_str(&serde_urlencoded::to_string([("account.id", account_id)]).unwrap());
+        }
+        self.client.get(format!(
+            "https://api.cloudflare.com/client/v4/zones?{query}"
+        ))
+    }
+
+    /// Every Cloudflare operation starts by looking up the zone id.
+    pub(crate) fn describe_request<'x>(&self, origin: impl IntoFqdn<'x>) -> PlannedRequest {
+        self.zone_lookup(&canonical_name(origin.into_name().as_ref()))
+            .plan()
     }
 
     async fn obtain_zone_id(&self, origin: impl IntoFqdn<'_>) -> crate::Result<String> {
-        let origin = origin.into_name();
+        let origin = canonical_name(origin.into_name().as_ref());
+        self.zone_ids
+            .get_or_update(origin.as_str(), || async {
+                self.zone_lookup(origin.as_str())
+                    .send::<ApiResult<Vec<IdMap>>>()
+                    .await
+                    .and_then(|r| r.unwrap_response("list zones"))
+                    .and_then(|result| {
+                        result
+                            .into_iter()
+                            .find(|zone| canonical_name(&zone.name) == origin)
+                            .map(|zone| zone.id)
+                            .ok_or_else(|| Error::ZoneNotFound(origin.clone()))
+                    })
+            })
+            .await
+    }
+
+    async fn obtain_record_id(
+        &self,
+        zone_id: &str,
+        name: impl IntoFqdn<'_>,
+        record_type: Option<DnsRecordType>,
+    ) -> crate::Result<String> {
+        let name = canonical_name(name.into_name().as_ref());
+        let query = match record_type {
+            Some(record_type) => Query::name_and_type(name.as_str(), record_type),
+            None => Query::name(name.as_str()),
+        };
         self.client
             .get(format!(
-                "https://api.cloudflare.com/client/v4/zones?{}",
-                Query::name(origin.as_ref()).serialize()
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{}",
+                query.serialize()
             ))
             .send::<ApiResult<Vec<IdMap>>>()
             .await
-            .and_then(|r| r.unwrap_response("list zones"))
+            .and_then(|r| r.unwrap_response("list DNS records"))
             .and_then(|result| {
                 result
                     .into_iter()
-                    .find(|zone| zone.name == origin.as_ref())
-                    .map(|zone| zone.id)
-                    .ok_or_else(|| Error::Api(format!("Zone {} not found", origin.as_ref())))
+                    .find(|record| canonical_name(&record.name) == name)
+                    .map(|record| record.id)
+                    .ok_or_else(|| Error::RecordNotFound(name.clone()))
             })
     }
 
-    async fn obtain_record_id(
+    /// The IDs of every record of `record_type` at `name`, failing with
+    /// [`Error::RecordNotFound`] when there are none.
+    async fn obtain_record_ids(
         &self,
         zone_id: &str,
         name: impl IntoFqdn<'_>,
-    ) -> crate::Result<String> {
-        let name = name.into_name();
-        self.client
+        record_type: DnsRecordType,
+    ) -> crate::Result<Vec<String>> {
+        let name = canonical_name(name.into_name().as_ref());
+        let record_ids = self
+            .client
             .get(format!(
                 "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{}",
-                Query::name(name.as_ref()).serialize()
+                Query::name_and_type(name.as_str(), record_type).serialize()
             ))
             .send::<ApiResult<Vec<IdMap>>>()
             .await
+            .and_then(|r| r.unwrap_response("list DNS records"))?
+            .into_iter()
+            .filter(|record| canonical_name(&record.name) == name)
+            .map(|record| record.id)
+            .collect::<Vec<_>>();
+
+        if record_ids.is_empty() {
+            Err(Error::RecordNotFound(name))
+        } else {
+            Ok(record_ids)
+        }
+    }
+
+    pub(crate) async fn get_all(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(DnsRecord, u32)>> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let name = canonical_name(name.into_name().as_ref());
+        self.client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{}",
+                Query::name_and_type(name.as_str(), record_type).serialize()
+            ))
+            .send::<ApiResult<Vec<DnsRecordEntry>>>()
+            .await
             .and_then(|r| r.unwrap_response("list DNS records"))
             .and_then(|result| {
                 result
                     .into_iter()
-                    .find(|record| record.name == name.as_ref())
-                    .map(|record| record.id)
-                    .ok_or_else(|| Error::Api(format!("DNS Record {} not found", name.as_ref())))
+                    .filter(|record| canonical_name(&record.name) == name)
+                    .map(|record| Ok((record.content.try_into()?, record.ttl)))
+                    .collect()
             })
     }
 
+    pub(crate) async fn types_at(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<DnsRecordType>> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let name = canonical_name(name.into_name().as_ref());
+        let mut types = Vec::new();
+
+        for record in self
+            .client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{}",
+                Query::name(name.as_str()).serialize()
+            ))
+            .send::<ApiResult<Vec<TypeMap>>>()
+            .await
+            .and_then(|r| r.unwrap_response("list DNS records"))?
+        {
+            if let Ok(record_type) = record.record_type.parse::<DnsRecordType>() {
+                if canonical_name(&record.name) == name && !types.contains(&record_type) {
+                    types.push(record_type);
+                }
+            }
+        }
+
+        Ok(types)
+    }
+
+    pub(crate) async fn get_raw(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Value> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let name = canonical_name(name.into_name().as_ref());
+        self.client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{}",
+                Query::name_and_type(name.as_str(), record_type).serialize()
+            ))
+            .send::<ApiResult<Vec<Value>>>()
+            .await
+            .and_then(|r| r.unwrap_response("list DNS records"))?
+            .into_iter()
+            .find(|record| {
+                record
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .is_some_and(|record_name| canonical_name(record_name) == name)
+            })
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))
+    }
+
+    pub(crate) async fn zone_nameservers(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<String>> {
+        self.client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{}",
+                self.obtain_zone_id(origin).await?
+            ))
+            .send::<ApiResult<ZoneDetails>>()
+            .await
+            .and_then(|r| r.unwrap_response("get zone"))
+            .map(|zone| zone.name_servers)
+    }
+
+    pub(crate) async fn list_zones(&self) -> crate::Result<Vec<String>> {
+        let mut zones = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let response = self
+                .client
+                .get(format!(
+                    "https://api.cloudflare.com/client/v4/zones?page={page}&per_page=50"
+                ))
+                .send::<ApiResult<Vec<IdMap>>>()
+                .await?;
+            let total_pages = response
+                .result_info
+                .as_ref()
+                .map_or(1, |info| info.total_pages);
+
+            zones.extend(
+                response
+                    .unwrap_response("list zones")?
+                    .into_iter()
+                    .map(|zone| zone.name),
+            );
+
+            if page >= total_pages {
+                return Ok(zones);
+            }
+            page += 1;
+        }
+    }
+
+    pub(crate) async fn list(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(String, DnsRecord, u32)>> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let mut records = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let response = self
+                .client
+                .get(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?page={page}&per_page=100"
+                ))
+                .send::<ApiResult<Vec<Value>>>()
+                .await?;
+            let total_pages = response
+                .result_info
+                .as_ref()
+                .map_or(1, |info| info.total_pages);
+
+            // Records of types not supported by this library are skipped.
+            records.extend(
+                response
+                    .unwrap_response("list DNS records")?
+                    .into_iter()
+                    .filter_map(|record| serde_json::from_value::<DnsRecordEntry>(record).ok())
+                    .filter_map(|record| {
+                        Some((record.name, record.content.try_into().ok()?, record.ttl))
+                    }),
+            );
+
+            if page >= total_pages {
+                return Ok(records);
+            }
+            page += 1;
+        }
+    }
+
     pub(crate) async fn create(
         &self,
         name: impl IntoFqdn<'_>,
         record: DnsRecord,
         ttl: u32,
         origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        self.create_with_options(name, record, ttl, origin, CreateOptions::default())
+            .await
+    }
+
+    pub(crate) async fn create_with_options(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+        options: CreateOptions,
     ) -> crate::Result<()> {
         self.client
             .post(format!(
                 "https://api.cloudflare.com/client/v4/zones/{}/dns_records",
                 self.obtain_zone_id(origin).await?
             ))
-            .with_body(CreateDnsRecordParams {
-                ttl: ttl.into(),
-                priority: record.priority(),
-                proxied: false.into(),
-                name: name.into_name().as_ref(),
-                content: record.into(),
-            })?
-            .send::<ApiResult<Value>>()
+            .with_body_merged(
+                CreateDnsRecordParams::new(
+                    name.into_name().as_ref(),
+                    record,
+                    self.clamp_ttl(ttl),
+                    options.proxied,
+                )
+                .with_tags(options.tags),
+                options.extra,
+            )?
+            .with_idempotency_key()
+            .send_with_retry::<ApiResult<Value>>()
             .await
             .map_err(Into::into)
             .map(|_| ())
@@ -179,15 +656,20 @@ impl CloudflareProvider {
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
         let name = name.into_name();
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let record_id = self
+            .obtain_record_id(&zone_id, name.as_ref(), Some(record.as_type()))
+            .await?;
+
         self.client
             .patch(format!(
-                "https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}",
-                self.obtain_zone_id(origin).await?,
-                name.as_ref()
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records/{record_id}"
             ))
             .with_body(UpdateDnsRecordParams {
-                ttl: ttl.into(),
+                ttl: self.clamp_ttl(ttl).into(),
+                priority: record.priority(),
                 proxied: None,
+                tags: Vec::new(),
                 name: name.as_ref(),
                 content: record.into(),
             })?
@@ -197,13 +679,145 @@ impl CloudflareProvider {
             .map(|_| ())
     }
 
+    pub(crate) async fn rename(
+        &self,
+        old_name: impl IntoFqdn<'_>,
+        new_name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let new_name = new_name.into_name();
+        let record_ids = self
+            .obtain_record_ids(&zone_id, old_name, record_type)
+            .await?;
+
+        for record_id in record_ids {
+            self.client
+                .patch(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records/{record_id}"
+                ))
+                .with_body(RenameParams {
+                    name: new_name.as_ref(),
+                })?
+                .send::<ApiResult<Value>>()
+                .await
+                .and_then(|r| r.unwrap_response("rename DNS record"))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn apply_batch(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        batch: Batch,
+    ) -> crate::Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let mut deletes = Vec::with_capacity(batch.deletes.len());
+        for name in &batch.deletes {
+            deletes.push(BatchDelete {
+                id: self.obtain_record_id(&zone_id, name, None).await?,
+            });
+        }
+        let mut patches = Vec::with_capacity(batch.updates.len());
+        for (name, record, ttl) in &batch.updates {
+            patches.push(BatchPatch {
+                id: self
+                    .obtain_record_id(&zone_id, name, Some(record.as_type()))
+                    .await?,
+                params: UpdateDnsRecordParams {
+                    ttl: self.ttl_seconds(*ttl).into(),
+                    priority: record.priority(),
+                    proxied: None,
+                    tags: Vec::new(),
+                    name,
+                    content: record.clone().into(),
+                },
+            });
+        }
+        let posts = batch
+            .creates
+            .iter()
+            .map(|(name, record, ttl)| {
+                CreateDnsRecordParams::new(name, record.clone(), self.ttl_seconds(*ttl), false)
+            })
+            .collect::<Vec<_>>();
+        let expected = (deletes.len(), patches.len(), posts.len());
+
+        // The batch is atomic, a failed operation rolls back the others and is
+        // reported in `errors`.
+        let result = self
+            .client
+            .post(format!(
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records/batch"
+            ))
+            .with_body(BatchParams {
+                deletes,
+                patches,
+                posts,
+            })?
+            .send::<ApiResult<BatchResult>>()
+            .await
+            .and_then(|r| r.unwrap_response("apply DNS record batch"))?;
+
+        if (
+            result.deletes.len(),
+            result.patches.len(),
+            result.posts.len(),
+        ) == expected
+        {
+            Ok(())
+        } else {
+            Err(Error::Api(format!(
+                "Incomplete DNS record batch: expected {expected:?} (deletes, patches, posts), got ({}, {}, {})",
+                result.deletes.len(),
+                result.patches.len(),
+                result.posts.len()
+            )))
+        }
+    }
+
+    async fn set_dnssec_status(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        status: &'static str,
+    ) -> crate::Result<DnssecResult> {
+        self.client
+            .patch(format!(
+                "https://api.cloudflare.com/client/v4/zones/{}/dnssec",
+                self.obtain_zone_id(origin).await?
+            ))
+            .with_body(DnssecParams { status })?
+            .send::<ApiResult<DnssecResult>>()
+            .await
+            .and_then(|r| r.unwrap_response("update DNSSEC status"))
+    }
+
+    pub(crate) async fn enable_dnssec(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<DnssecKey> {
+        self.set_dnssec_status(origin, "active")
+            .await
+            .and_then(TryInto::try_into)
+    }
+
+    pub(crate) async fn disable_dnssec(&self, origin: impl IntoFqdn<'_>) -> crate::Result<()> {
+        self.set_dnssec_status(origin, "disabled").await.map(|_| ())
+    }
+
     pub(crate) async fn delete(
         &self,
         name: impl IntoFqdn<'_>,
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
         let zone_id = self.obtain_zone_id(origin).await?;
-        let record_id = self.obtain_record_id(&zone_id, name).await?;
+        let record_id = self.obtain_record_id(&zone_id, name, None).await?;
 
         self.client
             .delete(format!(
@@ -214,6 +828,153 @@ impl CloudflareProvider {
             .map_err(Into::into)
             .map(|_| ())
     }
+
+    pub(crate) async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let record_ids = self.obtain_record_ids(&zone_id, name, record_type).await?;
+
+        for record_id in record_ids {
+            self.client
+                .delete(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records/{record_id}",
+                ))
+                .send::<ApiResult<Value>>()
+                .await
+                .and_then(|r| r.unwrap_response("delete DNS record"))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: &DnsRecord,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let name = canonical_name(name.into_name().as_ref());
+        let record_id = self
+            .client
+            .get(format!(
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{}",
+                Query::name_and_type(name.as_str(), record.as_type()).serialize()
+            ))
+            .send::<ApiResult<Vec<DnsRecordEntry>>>()
+            .await
+            .and_then(|r| r.unwrap_response("list DNS records"))?
+            .into_iter()
+            .find(|entry| {
+                canonical_name(&entry.name) == name
+                    && DnsRecord::try_from(entry.content.clone())
+                        .is_ok_and(|content| content.content_eq(record))
+            })
+            .map(|entry| entry.id)
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))?;
+
+        self.client
+            .delete(format!(
+                "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records/{record_id}",
+            ))
+            .send::<ApiResult<Value>>()
+            .await
+            .map(|_| ())
+    }
+
+    pub(crate) async fn delete_by_tag(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        tag: &str,
+    ) -> crate::Result<()> {
+        let zone_id = self.obtain_zone_id(origin).await?;
+        let mut record_ids = Vec::new();
+        let mut page = 1;
+
+        // All matching ids are collected first, deleting while paginating
+        // would shift the pages.
+        loop {
+            let query = serde_urlencoded::to_string(TagQuery {
+                tag,
+                page,
+                per_page: 100,
+            })
+            .map_err(|err| Error::Serialize(format!("Failed to serialize request: {err}")))?;
+            let response = self
+                .client
+                .get(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records?{query}"
+                ))
+                .send::<ApiResult<Vec<IdMap>>>()
+                .await?;
+            let total_pages = response
+                .result_info
+                .as_ref()
+                .map_or(1, |info| info.total_pages);
+
+            record_ids.extend(
+                response
+                    .unwrap_response("list DNS records")?
+                    .into_iter()
+                    .map(|record| record.id),
+            );
+
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
+
+        for record_id in record_ids {
+            self.client
+                .delete(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{zone_id}/dns_records/{record_id}",
+                ))
+                .send::<ApiResult<Value>>()
+                .await
+                .and_then(|r| r.unwrap_response("delete DNS record"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> CreateDnsRecordParams<'a> {
+    /// Build the body of a record creation request. `proxied` is only honored
+    /// for record types that Cloudflare can proxy, other records are always
+    /// created unproxied.
+    pub fn new(name: &'a str, record: DnsRecord, ttl: u32, proxied: bool) -> Self {
+        let priority = record.priority();
+        let content = DnsContent::from(record);
+
+        Self {
+            ttl: ttl.into(),
+            priority,
+            proxied: (proxied && content.is_proxyable()).into(),
+            tags: Vec::new(),
+            name,
+            content,
+        }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+}
+
+impl DnsContent {
+    /// Whether Cloudflare can proxy this record (A, AAAA and CNAME only).
+    pub fn is_proxyable(&self) -> bool {
+        matches!(
+            self,
+            DnsContent::A { .. } | DnsContent::AAAA { .. } | DnsContent::CNAME { .. }
+        )
+    }
 }
 
 impl<T> ApiResult<T> {
@@ -231,7 +992,17 @@ impl<T> ApiResult<T> {
 
 impl Query {
     pub fn name(name: impl Into<String>) -> Self {
-        Self { name: name.into() }
+        Self {
+            name: name.into(),
+            record_type: None,
+        }
+    }
+
+    pub fn name_and_type(name: impl Into<String>, record_type: DnsRecordType) -> Self {
+        Self {
+            name: name.into(),
+            record_type: Some(record_type.as_str()),
+        }
     }
 
     pub fn serialize(&self) -> String {
@@ -248,7 +1019,866 @@ impl From<DnsRecord> for DnsContent {
             DnsRecord::NS { content } => DnsContent::NS { content },
             DnsRecord::MX { content, priority } => DnsContent::MX { content, priority },
             DnsRecord::TXT { content } => DnsContent::TXT { content },
-            DnsRecord::SRV { content, .. } => DnsContent::SRV { content },
+            DnsRecord::SRV {
+                content,
+                priority,
+                weight,
+                port,
+            } => DnsContent::SRV {
+                content: format!("{weight} {port} {content}"),
+                priority,
+            },
+            DnsRecord::CAA { flags, tag, value } => DnsContent::CAA {
+                data: CaaData { flags, tag, value },
+            },
+            DnsRecord::CERT {
+                cert_type,
+                key_tag,
+                algorithm,
+                content,
+            } => DnsContent::CERT {
+                data: CertData {
+                    cert_type,
+                    key_tag,
+                    algorithm,
+                    certificate: content,
+                },
+            },
+            DnsRecord::SMIMEA {
+                usage,
+                selector,
+                matching_type,
+                data,
+            } => DnsContent::SMIMEA {
+                data: SmimeaData {
+                    usage,
+                    selector,
+                    matching_type,
+                    certificate: data,
+                },
+            },
+            DnsRecord::OPENPGPKEY { content } => DnsContent::OPENPGPKEY { content },
+            // Cloudflare has no SPF type and SPF is left out of its capabilities,
+            // so such records are rejected before reaching the API.
+            DnsRecord::SPF { content } => DnsContent::TXT { content },
+        }
+    }
+}
+
+impl TryFrom<DnsContent> for DnsRecord {
+    type Error = Error;
+
+    fn try_from(content: DnsContent) -> crate::Result<Self> {
+        match content {
+            DnsContent::A { content } => Ok(DnsRecord::A { content }),
+            DnsContent::AAAA { content } => Ok(DnsRecord::AAAA { content }),
+            DnsContent::CNAME { content } => Ok(DnsRecord::CNAME { content }),
+            DnsContent::NS { content } => Ok(DnsRecord::NS { content }),
+            DnsContent::MX { content, priority } => Ok(DnsRecord::MX { content, priority }),
+            DnsContent::TXT { content } => Ok(DnsRecord::TXT { content }),
+            DnsContent::SRV { content, priority } => {
+                // Cloudflare returns "weight port target", optionally prefixed by the priority.
+                if content.split_ascii_whitespace().count() == 3 {
+                    DnsRecord::from_parts(DnsRecordType::SRV, &format!("{priority} {content}"))
+                } else {
+                    DnsRecord::from_parts(DnsRecordType::SRV, &content)
+                }
+            }
+            DnsContent::CAA { data } => Ok(DnsRecord::CAA {
+                flags: data.flags,
+                tag: data.tag,
+                value: data.value,
+            }),
+            DnsContent::CERT { data } => Ok(DnsRecord::CERT {
+                cert_type: data.cert_type,
+                key_tag: data.key_tag,
+                algorithm: data.algorithm,
+                content: data.certificate,
+            }),
+            DnsContent::SMIMEA { data } => Ok(DnsRecord::SMIMEA {
+                usage: data.usage,
+                selector: data.selector,
+                matching_type: data.matching_type,
+                data: data.certificate,
+            }),
+            DnsContent::OPENPGPKEY { content } => Ok(DnsRecord::OPENPGPKEY { content }),
+        }
+    }
+}
+
+impl TryFrom<DnssecResult> for DnssecKey {
+    type Error = Error;
+
+    fn try_from(result: DnssecResult) -> crate::Result<Self> {
+        let missing = || {
+            Error::Api(format!(
+                "DNSSEC key not available (status {})",
+                result.status
+            ))
+        };
+
+        Ok(DnssecKey {
+            key_tag: result.key_tag.ok_or_else(missing)?,
+            algorithm: result
+                .algorithm
+                .as_deref()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(missing)?,
+            digest_type: result
+                .digest_type
+                .as_deref()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(missing)?,
+            digest: result.digest.clone().ok_or_else(missing)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{
+        BatchPatch, CloudflareProvider, CreateDnsRecordParams, DnsRecordEntry,
+        UpdateDnsRecordParams,
+    };
+    use crate::{
+        http::tests::{MockResponse, MockRoute, MockServer},
+        http::RateLimitInfo,
+        Batch, CreateOptions, DnsRecord, DnsRecordType, DnssecKey, Error, Ttl,
+    };
+
+    const ZONES: &str = "/client/v4/zones";
+    const RECORDS: &str = "/client/v4/zones/z1/dns_records";
+
+    fn provider(server: &MockServer) -> CloudflareProvider {
+        CloudflareProvider::new("token", None::<String>, None)
+            .unwrap()
+            .map_client(|client| client.with_base_url(&server.url))
+    }
+
+    fn ok(result: Value) -> MockResponse {
+        MockResponse::Json(
+            200,
+            json!({ "success": true, "errors": [], "result": result }),
+        )
+    }
+
+    fn zone() -> MockRoute {
+        MockRoute::new(
+            "GET",
+            format!("{ZONES}?"),
+            ok(json!([{ "id": "z1", "name": "example.com" }])),
+        )
+    }
+
+    fn record_ids(ids: &[&str]) -> MockResponse {
+        ok(ids
+            .iter()
+            .map(|id| json!({ "id": id, "name": "www.example.com" }))
+            .collect())
+    }
+
+    fn srv() -> DnsRecord {
+        DnsRecord::SRV {
+            content: "sip.example.com".to_string(),
+            priority: 10,
+            weight: 20,
+            port: 5060,
         }
     }
+
+    #[test]
+    fn update_params_carry_srv_priority() {
+        let record = srv();
+        let params = UpdateDnsRecordParams {
+            ttl: Some(300),
+            priority: record.priority(),
+            proxied: None,
+            tags: Vec::new(),
+            name: "_sip._tcp.example.com",
+            content: record.into(),
+        };
+        let body = serde_json::to_value(BatchPatch {
+            id: "abc".to_string(),
+            params,
+        })
+        .unwrap();
+
+        assert_eq!(body["id"], json!("abc"));
+        assert_eq!(body["type"], json!("SRV"));
+        assert_eq!(body["priority"], json!(10));
+        assert_eq!(body["content"], json!("20 5060 sip.example.com"));
+        assert_eq!(body.get("tags"), None::<&Value>);
+    }
+
+    #[test]
+    fn srv_priority_is_read_back() {
+        let entry = serde_json::from_value::<DnsRecordEntry>(json!({
+            "id": "abc",
+            "type": "SRV",
+            "name": "_sip._tcp.example.com",
+            "content": "20 5060 sip.example.com",
+            "priority": 10,
+            "ttl": 300,
+        }))
+        .unwrap();
+        let record = DnsRecord::try_from(entry.content).unwrap();
+
+        assert_eq!(record, srv());
+        assert!(record.content_eq(&srv()));
+    }
+
+    #[tokio::test]
+    async fn record_lookups_filter_by_type() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new("GET", RECORDS, record_ids(&["r1"])),
+            MockRoute::new("PATCH", RECORDS, ok(json!({}))),
+        ])
+        .await;
+
+        provider(&server)
+            .update(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        let lookups = server.requests_to("GET", RECORDS);
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(
+            lookups[0].path,
+            format!("{RECORDS}?name=www.example.com&type=A")
+        );
+    }
+
+    #[tokio::test]
+    async fn enable_dnssec_returns_ds_data() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "PATCH",
+                format!("{ZONES}/z1/dnssec"),
+                ok(json!({
+                    "status": "pending",
+                    "key_tag": 42,
+                    "algorithm": "13",
+                    "digest_type": "2",
+                    "digest": "48E939042E82C22542CB377B580DFDC52A361CEFDC72E7F9107E2B6BD9306A45",
+                    "flags": 257,
+                })),
+            ),
+        ])
+        .await;
+
+        let key = provider(&server)
+            .enable_dnssec("example.com")
+            .await
+            .unwrap();
+        assert_eq!(
+            key,
+            DnssecKey {
+                key_tag: 42,
+                algorithm: 13,
+                digest_type: 2,
+                digest: "48E939042E82C22542CB377B580DFDC52A361CEFDC72E7F9107E2B6BD9306A45"
+                    .to_string(),
+            }
+        );
+        let requests = server.requests_to("PATCH", ZONES);
+        assert_eq!(requests[0].json(), json!({ "status": "active" }));
+    }
+
+    #[tokio::test]
+    async fn dnssec_without_ds_data_is_an_error() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "PATCH",
+                format!("{ZONES}/z1/dnssec"),
+                ok(json!({ "status": "disabled" })),
+            ),
+        ])
+        .await;
+
+        assert!(matches!(
+            provider(&server).enable_dnssec("example.com").await,
+            Err(Error::Api(err)) if err == "DNSSEC key not available (status disabled)"
+        ));
+    }
+
+    #[test]
+    fn only_address_records_are_proxied() {
+        let mx = CreateDnsRecordParams::new(
+            "example.com",
+            DnsRecord::MX {
+                content: "mail.example.com".to_string(),
+                priority: 10,
+            },
+            300,
+            true,
+        );
+        let body = serde_json::to_value(mx).unwrap();
+        assert_eq!(body["type"], json!("MX"));
+        assert_eq!(body["proxied"], json!(false));
+
+        let a = CreateDnsRecordParams::new(
+            "www.example.com",
+            DnsRecord::A {
+                content: "192.0.2.1".parse().unwrap(),
+            },
+            300,
+            true,
+        );
+        assert_eq!(serde_json::to_value(a).unwrap()["proxied"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn tags_are_sent_and_used_for_deletion() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new("POST", RECORDS, ok(json!({}))),
+            MockRoute::new(
+                "GET",
+                format!("{RECORDS}?tag=owner%3Aacme&page=1"),
+                record_ids(&["r1", "r2"]),
+            ),
+            MockRoute::new("DELETE", RECORDS, ok(json!({}))),
+        ])
+        .await;
+        let provider = provider(&server);
+
+        provider
+            .create_with_options(
+                "_acme-challenge.example.com",
+                DnsRecord::TXT {
+                    content: "token".to_string(),
+                },
+                60,
+                "example.com",
+                CreateOptions {
+                    tags: vec!["owner:acme".to_string()],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let creates = server.requests_to("POST", RECORDS);
+        assert_eq!(creates[0].json()["tags"], json!(["owner:acme"]));
+
+        provider
+            .delete_by_tag("example.com", "owner:acme")
+            .await
+            .unwrap();
+        let deletes = server
+            .requests_to("DELETE", RECORDS)
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+        assert_eq!(deletes, [format!("{RECORDS}/r1"), format!("{RECORDS}/r2")]);
+    }
+
+    #[tokio::test]
+    async fn rejected_tag_deletes_are_errors() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new("GET", RECORDS, record_ids(&["r1", "r2"])),
+            MockRoute::new(
+                "DELETE",
+                RECORDS,
+                MockResponse::Json(
+                    200,
+                    json!({
+                        "success": false,
+                        "errors": [{ "code": 1000, "message": "Invalid user" }],
+                        "result": null,
+                    }),
+                ),
+            ),
+        ])
+        .await;
+
+        let result = provider(&server)
+            .delete_by_tag("example.com", "owner:acme")
+            .await;
+        assert!(matches!(result, Err(Error::Api(_))), "{result:?}");
+        assert_eq!(server.requests_to("DELETE", RECORDS).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_patches_by_record_id() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "GET",
+                RECORDS,
+                record_ids(&["372e67954025e0ba6aaa6d586b9e0b59"]),
+            ),
+            MockRoute::new("PATCH", RECORDS, ok(json!({}))),
+        ])
+        .await;
+
+        provider(&server)
+            .update(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        let updates = server.requests_to("PATCH", RECORDS);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(
+            updates[0].path,
+            format!("{RECORDS}/372e67954025e0ba6aaa6d586b9e0b59")
+        );
+        assert_eq!(updates[0].json()["name"], json!("www.example.com"));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_are_exposed() {
+        let server = MockServer::routes(vec![MockRoute::new(
+            "GET",
+            ZONES,
+            MockResponse::Headers(
+                200,
+                vec![
+                    ("X-RateLimit-Limit", "1200"),
+                    ("X-RateLimit-Remaining", "1187"),
+                    ("X-RateLimit-Reset", "300"),
+                ],
+                json!({ "success": true, "errors": [], "result": [] }),
+            ),
+        )])
+        .await;
+        let provider = provider(&server);
+        assert_eq!(provider.last_rate_limit(), None);
+
+        provider.list_zones().await.unwrap();
+        assert_eq!(
+            provider.last_rate_limit(),
+            Some(RateLimitInfo {
+                limit: Some(1200),
+                remaining: Some(1187),
+                reset: Some(300),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_zones_and_records_are_distinguished() {
+        let server = MockServer::routes(vec![
+            MockRoute::new("GET", format!("{ZONES}?name=example.org"), ok(json!([]))),
+            zone(),
+            MockRoute::new("GET", RECORDS, ok(json!([]))),
+        ])
+        .await;
+        let provider = provider(&server);
+        let record = DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+
+        assert!(matches!(
+            provider
+                .update("www.example.org", record.clone(), 300, "example.org")
+                .await,
+            Err(Error::ZoneNotFound(zone)) if zone == "example.org"
+        ));
+        assert!(matches!(
+            provider
+                .update("www.example.com", record, 300, "example.com")
+                .await,
+            Err(Error::RecordNotFound(name)) if name == "www.example.com"
+        ));
+    }
+
+    #[tokio::test]
+    async fn extra_fields_are_merged_into_the_body() {
+        let server =
+            MockServer::routes(vec![zone(), MockRoute::new("POST", RECORDS, ok(json!({})))]).await;
+
+        provider(&server)
+            .create_with_options(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+                "example.com",
+                CreateOptions {
+                    extra: Some(json!({ "comment": "managed", "settings": { "ipv4_only": true } })),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let body = server.requests_to("POST", RECORDS)[0].json();
+        assert_eq!(body["comment"], json!("managed"));
+        assert_eq!(body["settings"], json!({ "ipv4_only": true }));
+        assert_eq!(body["type"], json!("A"));
+        assert_eq!(body["content"], json!("192.0.2.1"));
+    }
+
+    #[tokio::test]
+    async fn batch_is_sent_in_a_single_request() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "GET",
+                format!("{RECORDS}?name=old.example.com"),
+                ok(json!([{ "id": "r0", "name": "old.example.com" }])),
+            ),
+            MockRoute::new("GET", RECORDS, record_ids(&["r1"])),
+            MockRoute::new(
+                "POST",
+                format!("{RECORDS}/batch"),
+                ok(json!({ "deletes": [{}], "patches": [{}], "posts": [{}] })),
+            ),
+        ])
+        .await;
+        let a = |content: &str| DnsRecord::A {
+            content: content.parse().unwrap(),
+        };
+
+        provider(&server)
+            .apply_batch(
+                "example.com",
+                Batch::default()
+                    .create("new.example.com", a("192.0.2.3"), Ttl::Auto)
+                    .update("www.example.com", a("192.0.2.2"), 600)
+                    .delete("old.example.com"),
+            )
+            .await
+            .unwrap();
+
+        let posts = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.method == "POST")
+            .collect::<Vec<_>>();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].path, format!("{RECORDS}/batch"));
+        let body = posts[0].json();
+        assert_eq!(body["deletes"], json!([{ "id": "r0" }]));
+        assert_eq!(body["patches"][0]["id"], json!("r1"));
+        assert_eq!(body["patches"][0]["name"], json!("www.example.com"));
+        assert_eq!(body["patches"][0]["content"], json!("192.0.2.2"));
+        assert_eq!(body["patches"][0]["ttl"], json!(600));
+        assert_eq!(body["posts"][0]["name"], json!("new.example.com"));
+        assert_eq!(body["posts"][0]["type"], json!("A"));
+        assert_eq!(body["posts"][0]["content"], json!("192.0.2.3"));
+        assert_eq!(body["posts"][0]["ttl"], json!(1));
+    }
+
+    #[tokio::test]
+    async fn incomplete_batch_results_are_an_error() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "POST",
+                format!("{RECORDS}/batch"),
+                ok(json!({ "posts": [] })),
+            ),
+        ])
+        .await;
+
+        let result = provider(&server)
+            .apply_batch(
+                "example.com",
+                Batch::default().create(
+                    "new.example.com",
+                    DnsRecord::A {
+                        content: "192.0.2.3".parse().unwrap(),
+                    },
+                    300,
+                ),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Api(_))), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn delete_value_removes_only_the_matching_record() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "GET",
+                RECORDS,
+                ok(json!([
+                    { "id": "r1", "name": "_acme-challenge.example.com", "type": "TXT", "content": "\"first\"", "ttl": 60 },
+                    { "id": "r2", "name": "_acme-challenge.example.com", "type": "TXT", "content": "\"second\"", "ttl": 60 },
+                ])),
+            ),
+            MockRoute::new("DELETE", RECORDS, ok(json!({}))),
+        ])
+        .await;
+
+        provider(&server)
+            .delete_value(
+                "_acme-challenge.example.com",
+                &DnsRecord::TXT {
+                    content: "second".to_string(),
+                },
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        let deletes = server.requests_to("DELETE", RECORDS);
+        assert_eq!(deletes.len(), 1);
+        assert_eq!(deletes[0].path, format!("{RECORDS}/r2"));
+    }
+
+    #[tokio::test]
+    async fn rejected_rrset_deletes_are_errors() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new("GET", RECORDS, record_ids(&["r1"])),
+            MockRoute::new(
+                "DELETE",
+                RECORDS,
+                MockResponse::Json(
+                    200,
+                    json!({
+                        "success": false,
+                        "errors": [{ "code": 7003, "message": "Could not route to /zones/z1/dns_records/r1" }],
+                        "result": null,
+                    }),
+                ),
+            ),
+        ])
+        .await;
+
+        let result = provider(&server)
+            .delete_rrset("www.example.com", DnsRecordType::A, "example.com")
+            .await;
+        assert!(matches!(result, Err(Error::Api(_))), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn rename_patches_only_the_name() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new("GET", RECORDS, record_ids(&["r1"])),
+            MockRoute::new("PATCH", RECORDS, ok(json!({}))),
+        ])
+        .await;
+
+        provider(&server)
+            .rename(
+                "www.example.com",
+                "web.example.com",
+                DnsRecordType::A,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        let patches = server.requests_to("PATCH", RECORDS);
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path, format!("{RECORDS}/r1"));
+        assert_eq!(patches[0].json(), json!({ "name": "web.example.com" }));
+        assert!(server.requests_to("DELETE", RECORDS).is_empty());
+        assert!(server.requests_to("POST", RECORDS).is_empty());
+    }
+
+    #[test]
+    fn certificate_records_use_structured_data() {
+        let record = DnsRecord::SMIMEA {
+            usage: 3,
+            selector: 1,
+            matching_type: 1,
+            data: "d2abde240d7cd3ee".to_string(),
+        };
+        let params =
+            CreateDnsRecordParams::new("_smimecert.example.com", record.clone(), 300, false);
+        let body = serde_json::to_value(&params).unwrap();
+
+        assert_eq!(body["type"], json!("SMIMEA"));
+        assert_eq!(
+            body["data"],
+            json!({ "usage": 3, "selector": 1, "matching_type": 1, "certificate": "d2abde240d7cd3ee" })
+        );
+
+        let entry = serde_json::from_value::<DnsRecordEntry>(json!({
+            "id": "abc",
+            "name": "_smimecert.example.com",
+            "ttl": 300,
+            "type": "SMIMEA",
+            "data": body["data"],
+        }))
+        .unwrap();
+        assert_eq!(DnsRecord::try_from(entry.content).unwrap(), record);
+    }
+
+    #[tokio::test]
+    async fn types_at_collects_distinct_types() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "GET",
+                RECORDS,
+                ok(json!([
+                    { "name": "www.example.com", "type": "A" },
+                    { "name": "www.example.com", "type": "TXT" },
+                    { "name": "www.example.com", "type": "A" },
+                    { "name": "www.example.com", "type": "HTTPS" },
+                ])),
+            ),
+        ])
+        .await;
+
+        assert_eq!(
+            provider(&server)
+                .types_at("www.example.com", "example.com")
+                .await
+                .unwrap(),
+            [DnsRecordType::A, DnsRecordType::TXT]
+        );
+        assert_eq!(
+            server.requests_to("GET", RECORDS)[0].path,
+            format!("{RECORDS}?name=www.example.com")
+        );
+    }
+
+    #[tokio::test]
+    async fn raw_records_are_passed_through() {
+        let record = json!({
+            "id": "r1",
+            "name": "www.example.com",
+            "type": "A",
+            "content": "192.0.2.1",
+            "ttl": 1,
+            "proxied": true,
+            "comment": "managed",
+            "tags": ["env:prod"],
+            "meta": { "auto_added": false },
+        });
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new("GET", RECORDS, ok(json!([record]))),
+        ])
+        .await;
+
+        assert_eq!(
+            provider(&server)
+                .get_raw("www.example.com", DnsRecordType::A, "example.com")
+                .await
+                .unwrap(),
+            record
+        );
+    }
+
+    #[tokio::test]
+    async fn zone_nameservers_are_read_from_the_zone() {
+        let server = MockServer::routes(vec![
+            zone(),
+            MockRoute::new(
+                "GET",
+                format!("{ZONES}/z1"),
+                ok(json!({
+                    "id": "z1",
+                    "name": "example.com",
+                    "name_servers": ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"],
+                })),
+            ),
+        ])
+        .await;
+
+        assert_eq!(
+            provider(&server)
+                .zone_nameservers("example.com")
+                .await
+                .unwrap(),
+            ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn low_ttls_are_clamped_when_enabled() {
+        let server =
+            MockServer::routes(vec![zone(), MockRoute::new("POST", RECORDS, ok(json!({})))]).await;
+        let a = || DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+        let strict = provider(&server);
+        let clamped = provider(&server).with_clamp_ttl(true);
+
+        strict
+            .create("www.example.com", a(), 30, "example.com")
+            .await
+            .unwrap();
+        for ttl in [30, 1, 300] {
+            clamped
+                .create("www.example.com", a(), ttl, "example.com")
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            server
+                .requests_to("POST", RECORDS)
+                .into_iter()
+                .map(|request| request.json()["ttl"].clone())
+                .collect::<Vec<_>>(),
+            [json!(30), json!(60), json!(1), json!(300)]
+        );
+    }
+
+    #[tokio::test]
+    async fn zone_lookups_are_scoped_to_the_account() {
+        let server = MockServer::routes(vec![
+            MockRoute::new(
+                "GET",
+                format!("{ZONES}?name=example.com&account.id=a2"),
+                ok(json!([{ "id": "z2", "name": "example.com" }])),
+            ),
+            MockRoute::new(
+                "GET",
+                format!("{ZONES}?"),
+                ok(json!([
+                    { "id": "z1", "name": "example.com" },
+                    { "id": "z2", "name": "example.com" },
+                ])),
+            ),
+            MockRoute::new("POST", ZONES, ok(json!({}))),
+        ])
+        .await;
+        let a = || DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+
+        provider(&server)
+            .create("www.example.com", a(), 300, "example.com")
+            .await
+            .unwrap();
+        provider(&server)
+            .with_account_id("a2".to_string())
+            .create("www.example.com", a(), 300, "example.com")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server
+                .requests_to("POST", ZONES)
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [
+                "/client/v4/zones/z1/dns_records",
+                "/client/v4/zones/z2/dns_records"
+            ]
+        );
+    }
 }
@@ -0,0 +1,562 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    canonical_name,
+    http::{HttpClient, HttpClientBuilder, PlannedRequest, RateLimitInfo},
+    Capabilities, Change, DnsRecord, DnsRecordType, Error, IntoFqdn, MetricsCallback,
+};
+
+use super::{concat_txt, require_credential};
+
+const ENDPOINT: &str = "https://api.dreamhost.com/";
+
+#[derive(Clone)]
+pub struct DreamhostProvider {
+    client: HttpClientBuilder,
+    api_key: String,
+    metrics: Option<MetricsCallback>,
+}
+
+#[derive(Serialize, Debug)]
+struct Command<'a> {
+    key: &'a str,
+    cmd: &'static str,
+    format: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record: Option<&'a str>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    record_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiResult {
+    result: String,
+    data: Value,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecordEntry {
+    pub zone: String,
+    pub record: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub value: String,
+}
+
+impl DreamhostProvider {
+    pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+        record_types: Cow::Borrowed(DnsRecordType::BASIC),
+        supports_list: true,
+        supports_multi_value: true,
+    };
+
+    pub(crate) fn new(api_key: impl AsRef<str>, timeout: Option<Duration>) -> crate::Result<Self> {
+        Ok(Self {
+            client: HttpClientBuilder::default().with_timeout(timeout),
+            api_key: require_credential(api_key.as_ref())?.to_string(),
+            metrics: None,
+        })
+    }
+
+    pub(crate) fn map_client(
+        mut self,
+        f: impl FnOnce(HttpClientBuilder) -> HttpClientBuilder,
+    ) -> Self {
+        self.client = f(self.client);
+        self
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.client.timeout()
+    }
+
+    pub(crate) fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        self.client.last_rate_limit()
+    }
+
+    pub(crate) fn with_metrics(mut self, metrics: MetricsCallback) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&MetricsCallback> {
+        self.metrics.as_ref()
+    }
+
+    fn request(
+        &self,
+        cmd: &'static str,
+        record: Option<&str>,
+        record_type: Option<&str>,
+        value: Option<&str>,
+    ) -> crate::Result<HttpClient> {
+        let query = serde_urlencoded::to_string(Command {
+            key: &self.api_key,
+            cmd,
+            format: "json",
+            record,
+            record_type,
+            value,
+        })
+        .map_err(|err| Error::Serialize(format!("Failed to serialize request: {err}")))?;
+        Ok(self.client.get(format!("{ENDPOINT}?{query}")))
+    }
+
+    /// Creates are sent as is, updates and deletes start by listing the
+    /// records to find the one to remove.
+    pub(crate) fn describe_request(&self, change: &Change) -> crate::Result<PlannedRequest> {
+        match change {
+            Change::Create { name, record, .. } => self.request(
+                "dns-add_record",
+                Some(name.as_str().into_name().as_ref()),
+                Some(record.as_type().as_str()),
+                Some(&record_value(record.clone())),
+            ),
+            Change::Update { .. } | Change::Delete { .. } => {
+                self.request("dns-list_records", None, None, None)
+            }
+        }
+        .map(|request| request.plan())
+    }
+
+    async fn send<T>(
+        &self,
+        cmd: &'static str,
+        record: Option<&str>,
+        record_type: Option<&str>,
+        value: Option<&str>,
+    ) -> crate::Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let response = self
+            .request(cmd, record, record_type, value)?
+            .send::<ApiResult>()
+            .await?;
+
+        if response.result == "success" {
+            serde_json::from_value(response.data)
+                .map_err(|err| Error::Serialize(format!("Failed to deserialize response: {err}")))
+        } else {
+            Err(Error::Api(format!("Failed to {cmd}: {}", response.data)))
+        }
+    }
+
+    async fn list_records(&self, zone: &str) -> crate::Result<Vec<RecordEntry>> {
+        let zone = canonical_name(zone);
+        self.send::<Vec<RecordEntry>>("dns-list_records", None, None, None)
+            .await
+            .map(|records| {
+                records
+                    .into_iter()
+                    .filter(|record| canonical_name(&record.zone) == zone)
+                    .collect()
+            })
+    }
+
+    pub(crate) async fn list_zones(&self) -> crate::Result<Vec<String>> {
+        let mut zones = Vec::<String>::new();
+        for record in self
+            .send::<Vec<RecordEntry>>("dns-list_records", None, None, None)
+            .await?
+        {
+            if !zones.contains(&record.zone) {
+                zones.push(record.zone);
+            }
+        }
+        Ok(zones)
+    }
+
+    async fn remove_record(&self, record: &RecordEntry) -> crate::Result<()> {
+        self.send::<Value>(
+            "dns-remove_record",
+            Some(&record.record),
+            Some(&record.record_type),
+            Some(&record.value),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub(crate) async fn get_all(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(DnsRecord, u32)>> {
+        let name = canonical_name(name.into_name().as_ref());
+        self.list_records(origin.into_name().as_ref())
+            .await?
+            .into_iter()
+            .filter(|record| {
+                canonical_name(&record.record) == name && record.record_type == record_type.as_str()
+            })
+            .map(|record| Ok((DnsRecord::from_parts(record_type, &record.value)?, 0)))
+            .collect()
+    }
+
+    pub(crate) async fn get_raw(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Value> {
+        let name = canonical_name(name.into_name().as_ref());
+        let zone = canonical_name(origin.into_name().as_ref());
+        let field = |record: &Value, field: &str| {
+            record
+                .get(field)
+                .and_then(Value::as_str)
+                .map(canonical_name)
+        };
+        self.send::<Vec<Value>>("dns-list_records", None, None, None)
+            .await?
+            .into_iter()
+            .find(|record| {
+                field(record, "zone").as_ref() == Some(&zone)
+                    && field(record, "record").as_ref() == Some(&name)
+                    && record.get("type").and_then(Value::as_str) == Some(record_type.as_str())
+            })
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))
+    }
+
+    pub(crate) async fn list(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(String, DnsRecord, u32)>> {
+        Ok(self
+            .list_records(origin.into_name().as_ref())
+            .await?
+            .into_iter()
+            .filter_map(|record| {
+                let record_type = record.record_type.parse().ok()?;
+                Some((
+                    record.record,
+                    DnsRecord::from_parts(record_type, &record.value).ok()?,
+                    0,
+                ))
+            })
+            .collect())
+    }
+
+    pub(crate) async fn create(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        _ttl: u32,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let record_type = record.as_type();
+        self.send::<Value>(
+            "dns-add_record",
+            Some(name.into_name().as_ref()),
+            Some(record_type.as_str()),
+            Some(&record_value(record)),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub(crate) async fn update(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        // DreamHost has no update command. The old value is removed first, as a
+        // second CNAME at the same name is rejected, and restored if the new
+        // value can not be added.
+        let name = canonical_name(name.into_name().as_ref());
+        let record_type = record.as_type();
+        let existing = self
+            .list_records(origin.into_name().as_ref())
+            .await?
+            .into_iter()
+            .find(|entry| {
+                canonical_name(&entry.record) == name && entry.record_type == record_type.as_str()
+            })
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))?;
+
+        // DreamHost has no TTLs, a record with the same value is up to date.
+        if DnsRecord::from_parts(record_type, &existing.value)
+            .is_ok_and(|content| content.content_eq(&record))
+        {
+            return Ok(());
+        }
+
+        self.remove_record(&existing).await?;
+        if let Err(err) = self.create(name.as_str(), record, ttl, "").await {
+            self.send::<Value>(
+                "dns-add_record",
+                Some(&existing.record),
+                Some(&existing.record_type),
+                Some(&existing.value),
+            )
+            .await?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn delete(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = canonical_name(name.into_name().as_ref());
+        let existing = self
+            .list_records(origin.into_name().as_ref())
+            .await?
+            .into_iter()
+            .find(|entry| canonical_name(&entry.record) == name)
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))?;
+
+        self.remove_record(&existing).await
+    }
+
+    pub(crate) async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = canonical_name(name.into_name().as_ref());
+        let existing = self
+            .list_records(origin.into_name().as_ref())
+            .await?
+            .into_iter()
+            .filter(|entry| {
+                canonical_name(&entry.record) == name && entry.record_type == record_type.as_str()
+            })
+            .collect::<Vec<_>>();
+        if existing.is_empty() {
+            return Err(Error::RecordNotFound(name));
+        }
+
+        for entry in &existing {
+            self.remove_record(entry).await?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: &DnsRecord,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = canonical_name(name.into_name().as_ref());
+        let record_type = record.as_type();
+        let existing = self
+            .list_records(origin.into_name().as_ref())
+            .await?
+            .into_iter()
+            .find(|entry| {
+                canonical_name(&entry.record) == name
+                    && entry.record_type == record_type.as_str()
+                    && DnsRecord::from_parts(record_type, &entry.value)
+                        .is_ok_and(|content| content.content_eq(record))
+            })
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))?;
+
+        self.remove_record(&existing).await
+    }
+}
+
+fn record_value(record: DnsRecord) -> String {
+    match record {
+        DnsRecord::A { content } => content.to_string(),
+        DnsRecord::AAAA { content } => content.to_string(),
+        DnsRecord::CNAME { content } => content,
+        DnsRecord::NS { content } => content,
+        DnsRecord::MX { content, priority } => format!("{priority} {content}"),
+        DnsRecord::TXT { content } => concat_txt(content),
+        DnsRecord::SRV {
+            content,
+            priority,
+            weight,
+            port,
+        } => format!("{priority} {weight} {port} {content}"),
+        DnsRecord::CAA { flags, tag, value } => format!("{flags} {tag} \"{value}\""),
+        DnsRecord::CERT {
+            cert_type,
+            key_tag,
+            algorithm,
+            content,
+        } => format!("{cert_type} {key_tag} {algorithm} {content}"),
+        DnsRecord::SMIMEA {
+            usage,
+            selector,
+            matching_type,
+            data,
+        } => format!("{usage} {selector} {matching_type} {data}"),
+        DnsRecord::OPENPGPKEY { content } => content,
+        DnsRecord::SPF { content } => concat_txt(content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::DreamhostProvider;
+    use crate::{
+        http::tests::{MockResponse, MockRoute, MockServer},
+        DnsRecord,
+    };
+
+    const ADD: &str = "/?key=apikey&cmd=dns-add_record&format=json";
+    const LIST: &str = "/?key=apikey&cmd=dns-list_records&format=json";
+    const REMOVE: &str = "/?key=apikey&cmd=dns-remove_record&format=json";
+
+    fn provider(server: &MockServer) -> DreamhostProvider {
+        DreamhostProvider::new("apikey", None)
+            .unwrap()
+            .map_client(|client| client.with_base_url(&server.url))
+    }
+
+    fn success(data: serde_json::Value) -> MockResponse {
+        MockResponse::Json(200, json!({ "result": "success", "data": data }))
+    }
+
+    fn records() -> MockRoute {
+        MockRoute::new(
+            "GET",
+            LIST,
+            success(json!([
+                { "zone": "example.com", "record": "www.example.com", "type": "A", "value": "192.0.2.1" },
+                { "zone": "example.com", "record": "example.com", "type": "MX", "value": "10 mail.example.com" },
+                { "zone": "example.org", "record": "www.example.org", "type": "A", "value": "192.0.2.9" },
+            ])),
+        )
+    }
+
+    fn a(content: &str) -> DnsRecord {
+        DnsRecord::A {
+            content: content.parse().unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_record() {
+        let server = MockServer::routes(vec![MockRoute::new(
+            "GET",
+            ADD,
+            success(json!("record_added")),
+        )])
+        .await;
+
+        provider(&server)
+            .create("www.example.com", a("192.0.2.1"), 300, "example.com")
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].path,
+            format!("{ADD}&record=www.example.com&type=A&value=192.0.2.1")
+        );
+    }
+
+    #[tokio::test]
+    async fn list_records_of_zone() {
+        let server = MockServer::routes(vec![records()]).await;
+
+        let records = provider(&server).list("example.com").await.unwrap();
+        assert_eq!(
+            records,
+            [
+                ("www.example.com".to_string(), a("192.0.2.1"), 0),
+                (
+                    "example.com".to_string(),
+                    DnsRecord::MX {
+                        content: "mail.example.com".to_string(),
+                        priority: 10,
+                    },
+                    0
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn update_removes_before_adding() {
+        let server = MockServer::routes(vec![
+            records(),
+            MockRoute::new("GET", ADD, success(json!("record_added"))),
+            MockRoute::new("GET", REMOVE, success(json!("record_removed"))),
+        ])
+        .await;
+
+        provider(&server)
+            .update("www.example.com", a("192.0.2.2"), 300, "example.com")
+            .await
+            .unwrap();
+
+        let paths = server
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                LIST.to_string(),
+                format!("{REMOVE}&record=www.example.com&type=A&value=192.0.2.1"),
+                format!("{ADD}&record=www.example.com&type=A&value=192.0.2.2"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_add_restores_existing_record() {
+        let server = MockServer::routes(vec![
+            records(),
+            MockRoute::new("GET", REMOVE, success(json!("record_removed"))),
+            MockRoute::new(
+                "GET",
+                ADD,
+                MockResponse::Json(200, json!({ "result": "error", "data": "invalid_value" })),
+            )
+            .once(),
+            MockRoute::new("GET", ADD, success(json!("record_added"))),
+        ])
+        .await;
+
+        assert!(provider(&server)
+            .update("www.example.com", a("192.0.2.2"), 300, "example.com")
+            .await
+            .is_err());
+        assert_eq!(
+            server
+                .requests_to("GET", ADD)
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [
+                format!("{ADD}&record=www.example.com&type=A&value=192.0.2.2"),
+                format!("{ADD}&record=www.example.com&type=A&value=192.0.2.1"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,370 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::{
+    canonical_name,
+    http::{HttpClient, HttpClientBuilder, PlannedRequest, RateLimitInfo},
+    Capabilities, Change, DnsRecord, DnsRecordType, Error, IntoFqdn, MetricsCallback,
+};
+
+use super::require_credential;
+
+const ENDPOINT: &str = "https://www.duckdns.org/update";
+const DOMAIN: &str = "duckdns.org";
+
+#[derive(Clone)]
+pub struct DuckDnsProvider {
+    client: HttpClientBuilder,
+    token: String,
+    metrics: Option<MetricsCallback>,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct UpdateParams<'a> {
+    domains: &'a str,
+    token: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv6: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    txt: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    clear: bool,
+}
+
+impl DuckDnsProvider {
+    pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+        record_types: Cow::Borrowed(&[DnsRecordType::A, DnsRecordType::AAAA, DnsRecordType::TXT]),
+        supports_list: false,
+        supports_multi_value: false,
+    };
+
+    pub(crate) fn new(token: impl AsRef<str>, timeout: Option<Duration>) -> crate::Result<Self> {
+        Ok(Self {
+            client: HttpClientBuilder::default().with_timeout(timeout),
+            token: require_credential(token.as_ref())?.to_string(),
+            metrics: None,
+        })
+    }
+
+    pub(crate) fn map_client(
+        mut self,
+        f: impl FnOnce(HttpClientBuilder) -> HttpClientBuilder,
+    ) -> Self {
+        self.client = f(self.client);
+        self
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.client.timeout()
+    }
+
+    pub(crate) fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        self.client.last_rate_limit()
+    }
+
+    pub(crate) fn with_metrics(mut self, metrics: MetricsCallback) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&MetricsCallback> {
+        self.metrics.as_ref()
+    }
+
+    fn request(&self, params: UpdateParams<'_>) -> crate::Result<HttpClient> {
+        let query = serde_urlencoded::to_string(UpdateParams {
+            token: &self.token,
+            ..params
+        })
+        .map_err(|err| Error::Serialize(format!("Failed to serialize request: {err}")))?;
+        Ok(self.client.get(format!("{ENDPOINT}?{query}")))
+    }
+
+    pub(crate) fn describe_request(&self, change: &Change) -> crate::Result<PlannedRequest> {
+        match change {
+            Change::Create { name, record, .. } | Change::Update { name, record, .. } => {
+                let domain = subdomain(name)?;
+                self.request(UpdateParams {
+                    domains: &domain,
+                    ..update_params(record.clone())?
+                })
+            }
+            Change::Delete { name } => {
+                let domain = subdomain(name)?;
+                self.request(clear_params(&domain, DnsRecordType::A)?)
+            }
+        }
+        .map(|request| request.plan())
+    }
+
+    async fn send(&self, params: UpdateParams<'_>) -> crate::Result<()> {
+        let domains = params.domains;
+        let response = self.request(params)?.send_raw().await?;
+
+        match response.lines().next().map(str::trim) {
+            Some("OK") => Ok(()),
+            Some("KO") => Err(Error::Api(format!(
+                "DuckDNS rejected the update of {domains}"
+            ))),
+            _ => Err(Error::Api(format!(
+                "Unexpected DuckDNS response: {response}"
+            ))),
+        }
+    }
+
+    pub(crate) async fn create(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        _ttl: u32,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let domain = subdomain(name.into_name().as_ref())?;
+        self.send(UpdateParams {
+            domains: &domain,
+            ..update_params(record)?
+        })
+        .await
+    }
+
+    pub(crate) async fn update(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        // DuckDNS holds a single value per type, which every update replaces.
+        self.create(name, record, ttl, origin).await
+    }
+
+    pub(crate) async fn delete(
+        &self,
+        name: impl IntoFqdn<'_>,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        self.clear(name.into_name().as_ref(), DnsRecordType::A)
+            .await
+    }
+
+    pub(crate) async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        self.clear(name.into_name().as_ref(), record_type).await
+    }
+
+    pub(crate) async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: &DnsRecord,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        self.clear(name.into_name().as_ref(), record.as_type())
+            .await
+    }
+
+    /// Clear the addresses of the domain, or its TXT record. DuckDNS clears
+    /// both addresses at once.
+    async fn clear(&self, name: &str, record_type: DnsRecordType) -> crate::Result<()> {
+        let domain = subdomain(name)?;
+        self.send(clear_params(&domain, record_type)?).await
+    }
+}
+
+fn update_params(record: DnsRecord) -> crate::Result<UpdateParams<'static>> {
+    Ok(match record {
+        DnsRecord::A { content } => UpdateParams {
+            ip: Some(content.to_string()),
+            ..Default::default()
+        },
+        DnsRecord::AAAA { content } => UpdateParams {
+            ipv6: Some(content.to_string()),
+            ..Default::default()
+        },
+        DnsRecord::TXT { content } => UpdateParams {
+            txt: Some(content),
+            ..Default::default()
+        },
+        record => {
+            return Err(Error::Api(format!(
+                "{} records are not supported by DuckDNS",
+                record.as_type()
+            )))
+        }
+    })
+}
+
+fn clear_params(domain: &str, record_type: DnsRecordType) -> crate::Result<UpdateParams<'_>> {
+    let txt = match record_type {
+        DnsRecordType::A | DnsRecordType::AAAA => None,
+        DnsRecordType::TXT => Some(String::new()),
+        record_type => {
+            return Err(Error::Api(format!(
+                "{record_type} records are not supported by DuckDNS"
+            )))
+        }
+    };
+
+    Ok(UpdateParams {
+        domains: domain,
+        txt,
+        clear: true,
+        ..Default::default()
+    })
+}
+
+/// The DuckDNS domain a name belongs to, e.g. `myhome` for both
+/// `myhome.duckdns.org` and `_acme-challenge.myhome.duckdns.org`.
+fn subdomain(name: &str) -> crate::Result<String> {
+    let name = canonical_name(name);
+    name.strip_suffix(DOMAIN)
+        .and_then(|name| name.strip_suffix('.'))
+        .and_then(|name| name.rsplit('.').next())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| Error::Api(format!("{name} is not a DuckDNS domain")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{update_params, DuckDnsProvider, UpdateParams};
+    use crate::{
+        http::tests::{MockResponse, MockRoute, MockServer},
+        DnsRecord, DnsUpdater, Error,
+    };
+
+    fn updater(server: &MockServer) -> DnsUpdater {
+        DnsUpdater::DuckDns(
+            DuckDnsProvider::new("token", None)
+                .unwrap()
+                .map_client(|client| client.with_base_url(&server.url)),
+        )
+    }
+
+    #[test]
+    fn token_is_redacted_from_request_urls() {
+        let provider = DuckDnsProvider::new("s3cr3t-token", None).unwrap();
+        let request = provider
+            .request(UpdateParams {
+                domains: "myhome",
+                ..update_params(DnsRecord::A {
+                    content: [192, 0, 2, 1].into(),
+                })
+                .unwrap()
+            })
+            .unwrap();
+
+        let url = request.redacted_url();
+        assert!(!url.contains("s3cr3t-token"), "{url}");
+        assert_eq!(
+            url,
+            "https://www.duckdns.org/update?domains=myhome&token=<redacted>&ip=192.0.2.1"
+        );
+        assert_eq!(request.plan().url, url);
+    }
+
+    #[tokio::test]
+    async fn public_ip_is_written_to_the_a_record() {
+        let server = MockServer::routes(vec![
+            MockRoute::new("GET", "/ip", MockResponse::Status(200, "192.0.2.1\n")),
+            MockRoute::new("GET", "/update?", MockResponse::Status(200, "OK")),
+        ])
+        .await;
+
+        let ip = updater(&server)
+            .update_to_public_ip_using(
+                "myhome.duckdns.org",
+                "duckdns.org",
+                300,
+                format!("{}/ip", server.url),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(ip.to_string(), "192.0.2.1");
+        assert_eq!(
+            server
+                .requests_to("GET", "/update?")
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            ["/update?domains=myhome&token=token&ip=192.0.2.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn txt_records_are_set_and_cleared() {
+        let server = MockServer::routes(vec![MockRoute::new(
+            "GET",
+            "/update?",
+            MockResponse::Status(200, "OK"),
+        )])
+        .await;
+        let updater = updater(&server);
+        let txt = DnsRecord::TXT {
+            content: "challenge".to_string(),
+        };
+
+        updater
+            .create(
+                "_acme-challenge.myhome.duckdns.org",
+                txt.clone(),
+                60,
+                "duckdns.org",
+            )
+            .await
+            .unwrap();
+        updater
+            .delete_value("_acme-challenge.myhome.duckdns.org", txt, "duckdns.org")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server
+                .requests()
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>(),
+            [
+                "/update?domains=myhome&token=token&txt=challenge",
+                "/update?domains=myhome&token=token&txt=&clear=true",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_updates_are_errors() {
+        let server = MockServer::start(vec![MockResponse::Status(200, "KO")]).await;
+
+        let result = updater(&server)
+            .create(
+                "myhome.duckdns.org",
+                DnsRecord::A {
+                    content: [192, 0, 2, 1].into(),
+                },
+                300,
+                "duckdns.org",
+            )
+            .await;
+
+        assert!(matches!(result, Err(Error::Api(_))), "{result:?}");
+    }
+}
@@ -0,0 +1,660 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    canonical_name,
+    http::{HttpClient, HttpClientBuilder, PlannedRequest, RateLimitInfo},
+    qualify_name, strip_origin_from_name, ApiCacheManager, Capabilities, Change, CreateOptions,
+    DnsRecord, DnsRecordType, Error, IntoFqdn, MetricsCallback,
+};
+
+use super::{concat_txt, require_credential};
+
+#[derive(Clone)]
+pub struct InfomaniakProvider {
+    client: HttpClientBuilder,
+    record_ids: ApiCacheManager<u64>,
+    metrics: Option<MetricsCallback>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RecordParams<'a> {
+    #[serde(rename = "type")]
+    pub record_type: &'static str,
+    pub source: &'a str,
+    pub target: String,
+    pub ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecordEntry {
+    pub id: u64,
+    pub source: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub target: String,
+    pub ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiResult<T> {
+    result: String,
+    data: Option<T>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiError {
+    pub code: String,
+    pub description: Option<String>,
+}
+
+impl InfomaniakProvider {
+    pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+        record_types: Cow::Borrowed(DnsRecordType::BASIC),
+        supports_list: true,
+        supports_multi_value: true,
+    };
+
+    pub(crate) fn new(secret: impl AsRef<str>, timeout: Option<Duration>) -> crate::Result<Self> {
+        let client = HttpClientBuilder::default()
+            .with_header(
+                "Authorization",
+                format!("Bearer {}", require_credential(secret.as_ref())?),
+            )
+            .with_timeout(timeout);
+
+        Ok(Self {
+            client,
+            record_ids: ApiCacheManager::default(),
+            metrics: None,
+        })
+    }
+
+    pub(crate) fn map_client(
+        mut self,
+        f: impl FnOnce(HttpClientBuilder) -> HttpClientBuilder,
+    ) -> Self {
+        self.client = f(self.client);
+        self
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.client.timeout()
+    }
+
+    pub(crate) fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        self.client.last_rate_limit()
+    }
+
+    pub(crate) fn with_metrics(mut self, metrics: MetricsCallback) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&MetricsCallback> {
+        self.metrics.as_ref()
+    }
+
+    fn list_request(&self, zone: &str) -> HttpClient {
+        self.client
+            .get(format!("https://api.infomaniak.com/2/zones/{zone}/records"))
+    }
+
+    fn create_request(
+        &self,
+        zone: &str,
+        source: &str,
+        record: DnsRecord,
+        ttl: u32,
+        extra: Option<Value>,
+    ) -> crate::Result<HttpClient> {
+        let (record_type, target) = record_data(record);
+        self.client
+            .post(format!("https://api.infomaniak.com/2/zones/{zone}/records"))
+            .with_body_merged(
+                RecordParams {
+                    record_type,
+                    source,
+                    target,
+                    ttl,
+                },
+                extra,
+            )
+    }
+
+    /// Creates are sent as is, updates and deletes start by listing the
+    /// zone's records to find the record id.
+    pub(crate) fn describe_request<'x>(
+        &self,
+        change: &Change,
+        origin: impl IntoFqdn<'x>,
+    ) -> crate::Result<PlannedRequest> {
+        let zone = origin.into_name();
+        match change {
+            Change::Create { name, record, ttl } => self
+                .create_request(
+                    zone.as_ref(),
+                    &strip_origin_from_name(name, zone.as_ref()),
+                    record.clone(),
+                    *ttl,
+                    None,
+                )
+                .map(|request| request.plan()),
+            Change::Update { .. } | Change::Delete { .. } => {
+                Ok(self.list_request(zone.as_ref()).plan())
+            }
+        }
+    }
+
+    async fn list_records(&self, zone: &str) -> crate::Result<Vec<RecordEntry>> {
+        self.list_request(zone)
+            .send::<ApiResult<Vec<RecordEntry>>>()
+            .await
+            .and_then(|r| r.unwrap_response("list DNS records"))
+    }
+
+    async fn obtain_record_id(
+        &self,
+        zone: &str,
+        source: &str,
+        record_type: &str,
+    ) -> crate::Result<u64> {
+        self.record_ids
+            .get_or_update_retrying(&format!("{zone}/{source}/{record_type}"), || async {
+                self.list_records(zone)
+                    .await?
+                    .into_iter()
+                    .find(|record| {
+                        canonical_name(&record.source) == source
+                            && record.record_type == record_type
+                    })
+                    .map(|record| record.id)
+                    .ok_or_else(|| Error::RecordNotFound(source.to_string()))
+            })
+            .await
+    }
+
+    pub(crate) async fn get_all(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(DnsRecord, u32)>> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+        self.list_records(zone.as_ref())
+            .await?
+            .into_iter()
+            .filter(|record| {
+                canonical_name(&record.source) == source
+                    && record.record_type == record_type.as_str()
+            })
+            .map(|record| {
+                Ok((
+                    DnsRecord::from_parts(record_type, &record.target)?,
+                    record.ttl,
+                ))
+            })
+            .collect()
+    }
+
+    pub(crate) async fn get_raw(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Value> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+        self.client
+            .get(format!(
+                "https://api.infomaniak.com/2/zones/{}/records",
+                zone.as_ref()
+            ))
+            .send::<ApiResult<Vec<Value>>>()
+            .await
+            .and_then(|r| r.unwrap_response("list DNS records"))?
+            .into_iter()
+            .find(|record| {
+                record
+                    .get("source")
+                    .and_then(Value::as_str)
+                    .is_some_and(|record_source| canonical_name(record_source) == source)
+                    && record.get("type").and_then(Value::as_str) == Some(record_type.as_str())
+            })
+            .ok_or_else(|| Error::RecordNotFound(source.clone()))
+    }
+
+    pub(crate) async fn list(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(String, DnsRecord, u32)>> {
+        let zone = origin.into_name();
+        Ok(self
+            .list_records(zone.as_ref())
+            .await?
+            .into_iter()
+            .filter_map(|record| {
+                let record_type = record.record_type.parse().ok()?;
+                Some((
+                    qualify_name(&record.source, zone.as_ref()),
+                    DnsRecord::from_parts(record_type, &record.target).ok()?,
+                    record.ttl,
+                ))
+            })
+            .collect())
+    }
+
+    pub(crate) async fn create(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        self.create_with_options(name, record, ttl, origin, CreateOptions::default())
+            .await
+    }
+
+    pub(crate) async fn create_with_options(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+        options: CreateOptions,
+    ) -> crate::Result<()> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+
+        self.create_request(zone.as_ref(), &source, record, ttl, options.extra)?
+            .with_idempotency_key()
+            .send_with_retry::<ApiResult<Value>>()
+            .await
+            .and_then(|r| r.unwrap_response("create DNS record"))
+            .map(|_| ())
+    }
+
+    pub(crate) async fn update(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+        let (record_type, target) = record_data(record);
+        let record_id = self
+            .obtain_record_id(zone.as_ref(), &source, record_type)
+            .await?;
+
+        self.client
+            .put(format!(
+                "https://api.infomaniak.com/2/zones/{}/records/{record_id}",
+                zone.as_ref()
+            ))
+            .with_body(RecordParams {
+                record_type,
+                source: &source,
+                target,
+                ttl,
+            })?
+            .send::<ApiResult<Value>>()
+            .await
+            .and_then(|r| r.unwrap_response("update DNS record"))
+            .map(|_| ())
+    }
+
+    pub(crate) async fn delete(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+        let record = self
+            .list_records(zone.as_ref())
+            .await?
+            .into_iter()
+            .find(|record| canonical_name(&record.source) == source)
+            .ok_or_else(|| Error::RecordNotFound(source.clone()))?;
+
+        self.record_ids.invalidate(&format!(
+            "{}/{source}/{}",
+            zone.as_ref(),
+            record.record_type
+        ));
+        self.client
+            .delete(format!(
+                "https://api.infomaniak.com/2/zones/{}/records/{}",
+                zone.as_ref(),
+                record.id
+            ))
+            .send::<ApiResult<Value>>()
+            .await
+            .and_then(|r| r.unwrap_response("delete DNS record"))
+            .map(|_| ())
+    }
+
+    pub(crate) async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+        let record_ids = self
+            .list_records(zone.as_ref())
+            .await?
+            .into_iter()
+            .filter(|record| {
+                canonical_name(&record.source) == source
+                    && record.record_type == record_type.as_str()
+            })
+            .map(|record| record.id)
+            .collect::<Vec<_>>();
+        if record_ids.is_empty() {
+            return Err(Error::RecordNotFound(source));
+        }
+
+        self.record_ids.invalidate(&format!(
+            "{}/{source}/{}",
+            zone.as_ref(),
+            record_type.as_str()
+        ));
+        for record_id in record_ids {
+            self.client
+                .delete(format!(
+                    "https://api.infomaniak.com/2/zones/{}/records/{record_id}",
+                    zone.as_ref()
+                ))
+                .send::<ApiResult<Value>>()
+                .await
+                .and_then(|r| r.unwrap_response("delete DNS record"))?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: &DnsRecord,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let zone = origin.into_name();
+        let source = strip_origin_from_name(name.into_name().as_ref(), zone.as_ref());
+        let record_type = record.as_type();
+        let entry = self
+            .list_records(zone.as_ref())
+            .await?
+            .into_iter()
+            .find(|entry| {
+                canonical_name(&entry.source) == source
+                    && entry.record_type == record_type.as_str()
+                    && DnsRecord::from_parts(record_type, &entry.target)
+                        .is_ok_and(|content| content.content_eq(record))
+            })
+            .ok_or_else(|| Error::RecordNotFound(source.clone()))?;
+
+        self.record_ids
+            .invalidate(&format!("{}/{source}/{}", zone.as_ref(), entry.record_type));
+        self.client
+            .delete(format!(
+                "https://api.infomaniak.com/2/zones/{}/records/{}",
+                zone.as_ref(),
+                entry.id
+            ))
+            .send::<ApiResult<Value>>()
+            .await
+            .and_then(|r| r.unwrap_response("delete DNS record"))
+            .map(|_| ())
+    }
+}
+
+impl<T> ApiResult<T> {
+    fn unwrap_response(self, action_name: &str) -> crate::Result<T> {
+        match (self.result.as_str(), self.data) {
+            ("success", Some(data)) => Ok(data),
+            _ => Err(Error::Api(format!(
+                "Failed to {action_name}: {:?}",
+                self.error
+            ))),
+        }
+    }
+}
+
+fn record_data(record: DnsRecord) -> (&'static str, String) {
+    match record {
+        DnsRecord::A { content } => ("A", content.to_string()),
+        DnsRecord::AAAA { content } => ("AAAA", content.to_string()),
+        DnsRecord::CNAME { content } => ("CNAME", content),
+        DnsRecord::NS { content } => ("NS", content),
+        DnsRecord::MX { content, priority } => ("MX", format!("{priority} {content}")),
+        DnsRecord::TXT { content } => ("TXT", concat_txt(content)),
+        DnsRecord::SRV {
+            content,
+            priority,
+            weight,
+            port,
+        } => ("SRV", format!("{priority} {weight} {port} {content}")),
+        DnsRecord::CAA { flags, tag, value } => ("CAA", format!("{flags} {tag} \"{value}\"")),
+        DnsRecord::CERT {
+            cert_type,
+            key_tag,
+            algorithm,
+            content,
+        } => (
+            "CERT",
+            format!("{cert_type} {key_tag} {algorithm} {content}"),
+        ),
+        DnsRecord::SMIMEA {
+            usage,
+            selector,
+            matching_type,
+            data,
+        } => (
+            "SMIMEA",
+            format!("{usage} {selector} {matching_type} {data}"),
+        ),
+        DnsRecord::OPENPGPKEY { content } => ("OPENPGPKEY", content),
+        DnsRecord::SPF { content } => ("SPF", concat_txt(content)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Mock, ServerGuard};
+    use serde_json::json;
+
+    use super::{record_data, InfomaniakProvider};
+    use crate::{DnsRecord, Error};
+
+    const RECORDS: &str = "/2/zones/example.com/records";
+
+    fn provider(server: &ServerGuard) -> InfomaniakProvider {
+        InfomaniakProvider::new("token", None)
+            .unwrap()
+            .map_client(|client| client.with_base_url(server.url()))
+    }
+
+    fn success(
+        server: &mut ServerGuard,
+        method: &str,
+        path: &str,
+        data: serde_json::Value,
+    ) -> Mock {
+        server
+            .mock(method, path)
+            .match_header("authorization", "Bearer token")
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "result": "success", "data": data }).to_string())
+    }
+
+    fn records() -> serde_json::Value {
+        json!([
+            { "id": 10, "source": "@", "type": "MX", "target": "10 mail.example.com", "ttl": 3600 },
+            { "id": 11, "source": "www", "type": "A", "target": "192.0.2.1", "ttl": 3600 },
+        ])
+    }
+
+    #[tokio::test]
+    async fn create_posts_relative_source() {
+        let mut server = mockito::Server::new_async().await;
+        let create = success(&mut server, "POST", RECORDS, json!(1))
+            .match_body(Matcher::Json(
+                json!({ "type": "A", "source": "www", "target": "192.0.2.1", "ttl": 300 }),
+            ))
+            .create_async()
+            .await;
+
+        provider(&server)
+            .create(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        create.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn update_resolves_and_caches_record_id() {
+        let mut server = mockito::Server::new_async().await;
+        let list = success(&mut server, "GET", RECORDS, records())
+            .expect(1)
+            .create_async()
+            .await;
+        let update = success(&mut server, "PUT", &format!("{RECORDS}/10"), json!(true))
+            .match_body(Matcher::Json(
+                json!({ "type": "MX", "source": "@", "target": "20 mx.example.com", "ttl": 300 }),
+            ))
+            .expect(2)
+            .create_async()
+            .await;
+        let provider = provider(&server);
+        let record = DnsRecord::MX {
+            content: "mx.example.com".to_string(),
+            priority: 20,
+        };
+
+        for _ in 0..2 {
+            provider
+                .update("example.com", record.clone(), 300, "example.com")
+                .await
+                .unwrap();
+        }
+
+        list.assert_async().await;
+        update.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn delete_removes_record_by_id() {
+        let mut server = mockito::Server::new_async().await;
+        success(&mut server, "GET", RECORDS, records())
+            .create_async()
+            .await;
+        let delete = success(&mut server, "DELETE", &format!("{RECORDS}/11"), json!(true))
+            .expect(1)
+            .create_async()
+            .await;
+
+        provider(&server)
+            .delete("www.example.com", "example.com")
+            .await
+            .unwrap();
+        delete.assert_async().await;
+
+        assert!(matches!(
+            provider(&server)
+                .delete("ftp.example.com", "example.com")
+                .await,
+            Err(Error::RecordNotFound(source)) if source == "ftp"
+        ));
+    }
+
+    #[tokio::test]
+    async fn unauthorized() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", RECORDS)
+            .with_status(401)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({ "result": "error", "error": { "code": "not_authorized" } }).to_string(),
+            )
+            .create_async()
+            .await;
+
+        assert!(matches!(
+            provider(&server)
+                .delete("www.example.com", "example.com")
+                .await,
+            Err(Error::Unauthorized)
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_retries_record_lookup_until_listed() {
+        let mut server = mockito::Server::new_async().await;
+        let empty = success(&mut server, "GET", RECORDS, json!([]))
+            .expect(1)
+            .create_async()
+            .await;
+        let listed = success(&mut server, "GET", RECORDS, records())
+            .expect(1)
+            .create_async()
+            .await;
+        let update = success(&mut server, "PUT", &format!("{RECORDS}/11"), json!(true))
+            .expect(1)
+            .create_async()
+            .await;
+
+        provider(&server)
+            .update(
+                "www.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.2".parse().unwrap(),
+                },
+                300,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        empty.assert_async().await;
+        listed.assert_async().await;
+        update.assert_async().await;
+    }
+
+    #[test]
+    fn txt_strings_are_concatenated() {
+        assert_eq!(
+            record_data(DnsRecord::txt_from_strings(&["first", "second"])),
+            ("TXT", "firstsecond".to_string())
+        );
+    }
+}
@@ -9,12 +9,247 @@
  * except according to those terms.
  */
 
-use crate::DnsRecord;
+use std::{
+    borrow::Cow,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{DnsRecord, DnsRecordType, Error};
 
 pub mod cloudflare;
+pub mod dreamhost;
+pub mod duckdns;
+pub mod infomaniak;
+pub mod njalla;
 pub mod rfc2136;
 
+/// Reject credentials that are empty or only contain whitespace, which
+/// usually means an unset environment variable.
+pub(crate) fn require_credential(value: &str) -> crate::Result<&str> {
+    if value.trim().is_empty() {
+        Err(Error::Config("empty credential".to_string()))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Strip one layer of surrounding quotes from a TXT value and unescape it,
+/// so `"hello"` as stored by a provider compares equal to `hello`.
+pub fn canonical_txt(value: &str) -> Cow<'_, str> {
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(value) if value.contains('\\') => {
+            let mut result = String::with_capacity(value.len());
+            let mut chars = value.chars();
+            while let Some(ch) = chars.next() {
+                if ch == '\\' {
+                    if let Some(ch) = chars.next() {
+                        result.push(ch);
+                    }
+                } else {
+                    result.push(ch);
+                }
+            }
+            Cow::Owned(result)
+        }
+        Some(value) => Cow::Borrowed(value),
+        None => Cow::Borrowed(value),
+    }
+}
+
+/// Split a TXT value into its character-strings. A value made of quoted
+/// strings, such as `"v=DKIM1; " "p=MIIB..."`, yields each string unescaped.
+/// Any other value is returned as a single string.
+pub fn txt_strings(value: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut chars = value.trim().chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => string.extend(chars.next()),
+                        Some(ch) => string.push(ch),
+                        None => return vec![value.to_string()],
+                    }
+                }
+                strings.push(string);
+            }
+            ch if ch.is_ascii_whitespace() && !strings.is_empty() => {}
+            _ => return vec![value.to_string()],
+        }
+    }
+
+    if strings.is_empty() {
+        strings.push(value.to_string());
+    }
+    strings
+}
+
+/// Build a TXT value from its character-strings, quoting them when there is
+/// more than one so that [`txt_strings`] can split them again.
+pub fn join_txt_strings(strings: &[impl AsRef<str>]) -> String {
+    match strings {
+        [string] => string.as_ref().to_string(),
+        strings => strings
+            .iter()
+            .map(|string| {
+                format!(
+                    "\"{}\"",
+                    string.as_ref().replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// Concatenate the character-strings of a TXT value, for APIs that only
+/// accept a single string.
+pub(crate) fn concat_txt(content: String) -> String {
+    match txt_strings(&content) {
+        strings if strings.len() > 1 => strings.concat(),
+        _ => content,
+    }
+}
+
+/// Decode a hex string such as the data of an SMIMEA record.
+pub(crate) fn hex_decode(value: &str) -> crate::Result<Vec<u8>> {
+    let pairs = value.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(Error::Parse("Odd length hex string".to_string()));
+    }
+    pairs
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::Parse("Invalid hex string".to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub(crate) fn base64_decode(value: &str) -> crate::Result<Vec<u8>> {
+    STANDARD
+        .decode(value)
+        .map_err(|err| Error::Parse(format!("Invalid base64 data: {err}")))
+}
+
 impl DnsRecord {
+    /// Build a TXT record made of several character-strings, e.g. a DKIM key
+    /// longer than 255 bytes.
+    pub fn txt_from_strings(strings: &[impl AsRef<str>]) -> Self {
+        DnsRecord::TXT {
+            content: join_txt_strings(strings),
+        }
+    }
+
+    /// Compare the content of two records, ignoring quoting differences in TXT
+    /// values and how they are split into character-strings.
+    pub fn content_eq(&self, other: &DnsRecord) -> bool {
+        match (self, other) {
+            (DnsRecord::TXT { content: a }, DnsRecord::TXT { content: b })
+            | (DnsRecord::SPF { content: a }, DnsRecord::SPF { content: b }) => {
+                txt_strings(a).concat() == txt_strings(b).concat()
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Check that the record content is consistent with its type: targets of
+    /// `CNAME`, `NS`, `MX` and `SRV` records must be host names rather than IP
+    /// addresses, and CAA tags must be `issue`, `issuewild` or `iodef`. An SRV
+    /// or MX target of `.` is accepted, it marks the service as unavailable
+    /// (RFC 2782) or the domain as not accepting mail (RFC 7505).
+    /// CERT and OPENPGPKEY content must be base64, SMIMEA data hex.
+    pub fn validate(&self) -> crate::Result<()> {
+        match self {
+            DnsRecord::SRV { content, .. } | DnsRecord::MX { content, .. } if content == "." => {
+                Ok(())
+            }
+            DnsRecord::CNAME { content }
+            | DnsRecord::NS { content }
+            | DnsRecord::MX { content, .. }
+            | DnsRecord::SRV { content, .. } => {
+                let record_type = self.as_type();
+                let host = content.trim_end_matches('.');
+                if host.is_empty() {
+                    Err(Error::Parse(format!("{record_type} target is empty")))
+                } else if host.parse::<IpAddr>().is_ok() {
+                    Err(Error::Parse(format!(
+                        "{record_type} target {content:?} is an IP address, expected a host name"
+                    )))
+                } else if host.len() > 253
+                    || host.split('.').any(|label| {
+                        label.is_empty()
+                            || label.len() > 63
+                            || !label.bytes().all(|ch| {
+                                ch.is_ascii_alphanumeric() || matches!(ch, b'-' | b'_' | b'*')
+                            })
+                    })
+                {
+                    Err(Error::Parse(format!(
+                        "{record_type} target {content:?} is not a valid host name"
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+            DnsRecord::CAA { tag, .. }
+                if !matches!(tag.as_str(), "issue" | "issuewild" | "iodef") =>
+            {
+                Err(Error::Parse(format!(
+                    "Unsupported CAA tag {tag:?}, expected issue, issuewild or iodef"
+                )))
+            }
+            DnsRecord::CERT { content, .. } | DnsRecord::OPENPGPKEY { content } => {
+                base64_decode(content).map(|_| ())
+            }
+            DnsRecord::SMIMEA { data, .. } => hex_decode(data).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Build an `A` or `AAAA` record depending on the family of `ip`.
+    pub fn from_ip(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(content) => DnsRecord::A { content },
+            IpAddr::V6(content) => DnsRecord::AAAA { content },
+        }
+    }
+
+    /// Parse an MX record from its combined form, `10 mail.example.com`.
+    pub fn mx_from_str(value: &str) -> crate::Result<Self> {
+        Self::from_fields(DnsRecordType::MX, value, 2)
+    }
+
+    /// Parse an SRV record from its combined form, `10 20 443 sip.example.com`
+    /// (priority, weight, port and target).
+    pub fn srv_from_str(value: &str) -> crate::Result<Self> {
+        Self::from_fields(DnsRecordType::SRV, value, 4)
+    }
+
+    fn from_fields(record_type: DnsRecordType, value: &str, fields: usize) -> crate::Result<Self> {
+        if value.split_ascii_whitespace().count() == fields {
+            Self::from_parts(record_type, value)
+        } else {
+            Err(Error::Parse(format!(
+                "Invalid {record_type} record content: {value}"
+            )))
+        }
+    }
+
     pub fn priority(&self) -> Option<u16> {
         match self {
             DnsRecord::MX { priority, .. } => Some(*priority),
@@ -22,4 +257,415 @@ impl DnsRecord {
             _ => None,
         }
     }
+
+    /// The address of an `A` record.
+    pub fn as_ipv4(&self) -> Option<Ipv4Addr> {
+        match self {
+            DnsRecord::A { content } => Some(*content),
+            _ => None,
+        }
+    }
+
+    /// The address of an `AAAA` record.
+    pub fn as_ipv6(&self) -> Option<Ipv6Addr> {
+        match self {
+            DnsRecord::AAAA { content } => Some(*content),
+            _ => None,
+        }
+    }
+
+    /// The target host name of a `CNAME`, `NS`, `MX` or `SRV` record.
+    pub fn as_target(&self) -> Option<&str> {
+        match self {
+            DnsRecord::CNAME { content }
+            | DnsRecord::NS { content }
+            | DnsRecord::MX { content, .. }
+            | DnsRecord::SRV { content, .. } => Some(content),
+            _ => None,
+        }
+    }
+
+    pub fn as_type(&self) -> DnsRecordType {
+        match self {
+            DnsRecord::A { .. } => DnsRecordType::A,
+            DnsRecord::AAAA { .. } => DnsRecordType::AAAA,
+            DnsRecord::CNAME { .. } => DnsRecordType::CNAME,
+            DnsRecord::NS { .. } => DnsRecordType::NS,
+            DnsRecord::MX { .. } => DnsRecordType::MX,
+            DnsRecord::TXT { .. } => DnsRecordType::TXT,
+            DnsRecord::SRV { .. } => DnsRecordType::SRV,
+            DnsRecord::CAA { .. } => DnsRecordType::CAA,
+            DnsRecord::CERT { .. } => DnsRecordType::CERT,
+            DnsRecord::SMIMEA { .. } => DnsRecordType::SMIMEA,
+            DnsRecord::OPENPGPKEY { .. } => DnsRecordType::OPENPGPKEY,
+            DnsRecord::SPF { .. } => DnsRecordType::SPF,
+        }
+    }
+
+    /// Build a record from its type and its zone file representation
+    /// (e.g. `10 mail.example.org` for MX).
+    pub(crate) fn from_parts(record_type: DnsRecordType, content: &str) -> crate::Result<Self> {
+        let invalid = || Error::Parse(format!("Invalid {record_type} record content: {content}"));
+        let mut parts = content.split_ascii_whitespace();
+        let mut next = || parts.next().ok_or_else(invalid);
+        let parse_u16 = |part: &str| part.parse::<u16>().map_err(|_| invalid());
+
+        Ok(match record_type {
+            DnsRecordType::A => DnsRecord::A {
+                content: content.trim().parse().map_err(|_| invalid())?,
+            },
+            DnsRecordType::AAAA => DnsRecord::AAAA {
+                content: content.trim().parse().map_err(|_| invalid())?,
+            },
+            DnsRecordType::CNAME => DnsRecord::CNAME {
+                content: content.trim().to_string(),
+            },
+            DnsRecordType::NS => DnsRecord::NS {
+                content: content.trim().to_string(),
+            },
+            DnsRecordType::MX => {
+                let priority = parse_u16(next()?)?;
+                DnsRecord::MX {
+                    priority,
+                    content: next()?.to_string(),
+                }
+            }
+            DnsRecordType::TXT => DnsRecord::TXT {
+                content: content.to_string(),
+            },
+            DnsRecordType::SRV => {
+                let priority = parse_u16(next()?)?;
+                let weight = parse_u16(next()?)?;
+                let port = parse_u16(next()?)?;
+                DnsRecord::SRV {
+                    priority,
+                    weight,
+                    port,
+                    content: next()?.to_string(),
+                }
+            }
+            DnsRecordType::CAA => {
+                let mut parts = content.trim().splitn(3, ' ');
+                DnsRecord::CAA {
+                    flags: parts
+                        .next()
+                        .and_then(|flags| flags.parse().ok())
+                        .ok_or_else(invalid)?,
+                    tag: parts.next().ok_or_else(invalid)?.to_string(),
+                    value: canonical_txt(parts.next().ok_or_else(invalid)?.trim()).into_owned(),
+                }
+            }
+            // Base64 and hex blobs may be split by whitespace in zone files.
+            DnsRecordType::CERT => {
+                let cert_type = parse_u16(next()?)?;
+                let key_tag = parse_u16(next()?)?;
+                let algorithm = next()?.parse().map_err(|_| invalid())?;
+                DnsRecord::CERT {
+                    cert_type,
+                    key_tag,
+                    algorithm,
+                    content: parts.collect(),
+                }
+            }
+            DnsRecordType::SMIMEA => {
+                let mut parse_u8 = || next()?.parse::<u8>().map_err(|_| invalid());
+                DnsRecord::SMIMEA {
+                    usage: parse_u8()?,
+                    selector: parse_u8()?,
+                    matching_type: parse_u8()?,
+                    data: parts.collect(),
+                }
+            }
+            DnsRecordType::OPENPGPKEY => DnsRecord::OPENPGPKEY {
+                content: parts.collect(),
+            },
+            DnsRecordType::SPF => DnsRecord::SPF {
+                content: content.to_string(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{canonical_txt, concat_txt, txt_strings};
+    use crate::{DnsRecord, DnsRecordType, Error};
+
+    #[test]
+    fn validate_accepts_null_targets() {
+        for record in [
+            DnsRecord::MX {
+                content: ".".to_string(),
+                priority: 0,
+            },
+            DnsRecord::SRV {
+                content: ".".to_string(),
+                priority: 0,
+                weight: 0,
+                port: 0,
+            },
+        ] {
+            assert!(record.validate().is_ok(), "{record:?}");
+        }
+    }
+
+    #[test]
+    fn validate_rejects_bad_targets() {
+        for content in ["", "..", "192.0.2.1", "mail..example.com"] {
+            let record = DnsRecord::MX {
+                content: content.to_string(),
+                priority: 10,
+            };
+            assert!(
+                matches!(record.validate(), Err(Error::Parse(_))),
+                "{record:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_rejects_mismatched_content() {
+        for (record, message) in [
+            (
+                DnsRecord::CNAME {
+                    content: "192.0.2.1".to_string(),
+                },
+                "CNAME target \"192.0.2.1\" is an IP address, expected a host name",
+            ),
+            (
+                DnsRecord::NS {
+                    content: "2001:db8::1".to_string(),
+                },
+                "NS target \"2001:db8::1\" is an IP address, expected a host name",
+            ),
+            (
+                DnsRecord::SRV {
+                    content: "sip example.com".to_string(),
+                    priority: 10,
+                    weight: 20,
+                    port: 5060,
+                },
+                "SRV target \"sip example.com\" is not a valid host name",
+            ),
+            (
+                DnsRecord::CAA {
+                    flags: 0,
+                    tag: "issuer".to_string(),
+                    value: "letsencrypt.org".to_string(),
+                },
+                "Unsupported CAA tag \"issuer\", expected issue, issuewild or iodef",
+            ),
+        ] {
+            assert!(
+                matches!(record.validate(), Err(Error::Parse(err)) if err == message),
+                "{record:?}"
+            );
+        }
+
+        for record in [
+            DnsRecord::CNAME {
+                content: "_acme-challenge.example.net.".to_string(),
+            },
+            DnsRecord::CAA {
+                flags: 0,
+                tag: "issuewild".to_string(),
+                value: ";".to_string(),
+            },
+        ] {
+            assert!(record.validate().is_ok(), "{record:?}");
+        }
+    }
+
+    #[test]
+    fn records_from_ip() {
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        assert_eq!(
+            DnsRecord::from_ip(ip),
+            DnsRecord::A {
+                content: "192.0.2.1".parse().unwrap()
+            }
+        );
+        assert_eq!(DnsRecordType::for_ip(ip), DnsRecordType::A);
+
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(
+            DnsRecord::from_ip(ip),
+            DnsRecord::AAAA {
+                content: "2001:db8::1".parse().unwrap()
+            }
+        );
+        assert_eq!(DnsRecordType::for_ip(ip), DnsRecordType::AAAA);
+    }
+
+    #[test]
+    fn txt_comparison_ignores_quotes() {
+        assert_eq!(canonical_txt("\"hello\""), "hello");
+        assert_eq!(canonical_txt("\"say \\\"hi\\\"\""), "say \"hi\"");
+        assert_eq!(canonical_txt("hello"), "hello");
+
+        let desired = DnsRecord::TXT {
+            content: "hello".to_string(),
+        };
+        let stored = DnsRecord::TXT {
+            content: "\"hello\"".to_string(),
+        };
+        assert!(stored.content_eq(&desired));
+        assert!(!stored.content_eq(&DnsRecord::TXT {
+            content: "world".to_string(),
+        }));
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let a = DnsRecord::A {
+            content: "192.0.2.1".parse().unwrap(),
+        };
+        let aaaa = DnsRecord::AAAA {
+            content: "2001:db8::1".parse().unwrap(),
+        };
+        assert_eq!(a.as_ipv4(), Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(a.as_ipv6(), None);
+        assert_eq!(a.as_target(), None);
+        assert_eq!(aaaa.as_ipv6(), Some("2001:db8::1".parse().unwrap()));
+        assert_eq!(aaaa.as_ipv4(), None);
+
+        for record in [
+            DnsRecord::CNAME {
+                content: "host.example.com".to_string(),
+            },
+            DnsRecord::NS {
+                content: "host.example.com".to_string(),
+            },
+            DnsRecord::MX {
+                content: "host.example.com".to_string(),
+                priority: 10,
+            },
+            DnsRecord::SRV {
+                content: "host.example.com".to_string(),
+                priority: 10,
+                weight: 20,
+                port: 443,
+            },
+        ] {
+            assert_eq!(record.as_target(), Some("host.example.com"), "{record:?}");
+            assert_eq!(record.as_ipv4(), None);
+        }
+
+        let txt = DnsRecord::TXT {
+            content: "host.example.com".to_string(),
+        };
+        assert_eq!(txt.as_target(), None);
+    }
+
+    #[test]
+    fn multi_string_txt_round_trip() {
+        let record = DnsRecord::txt_from_strings(&["v=DKIM1; k=rsa; ", "p=MIIB\"x\""]);
+        let DnsRecord::TXT { content } = &record else {
+            unreachable!()
+        };
+        assert_eq!(content, "\"v=DKIM1; k=rsa; \" \"p=MIIB\\\"x\\\"\"");
+        assert_eq!(txt_strings(content), ["v=DKIM1; k=rsa; ", "p=MIIB\"x\""]);
+        assert_eq!(concat_txt(content.clone()), "v=DKIM1; k=rsa; p=MIIB\"x\"");
+        assert!(record.content_eq(&DnsRecord::TXT {
+            content: "v=DKIM1; k=rsa; p=MIIB\"x\"".to_string(),
+        }));
+
+        assert_eq!(
+            DnsRecord::txt_from_strings(&["hello"]),
+            DnsRecord::TXT {
+                content: "hello".to_string()
+            }
+        );
+        assert_eq!(txt_strings("not \"quoted"), ["not \"quoted"]);
+    }
+
+    #[test]
+    fn certificate_records_are_parsed_and_validated() {
+        assert_eq!(
+            DnsRecord::from_parts(DnsRecordType::CERT, "1 12345 8 MIIB CgKC AQEA").unwrap(),
+            DnsRecord::CERT {
+                cert_type: 1,
+                key_tag: 12345,
+                algorithm: 8,
+                content: "MIIBCgKCAQEA".to_string(),
+            }
+        );
+        assert_eq!(
+            DnsRecord::from_parts(DnsRecordType::SMIMEA, "3 1 1 d2abde24 0d7cd3ee").unwrap(),
+            DnsRecord::SMIMEA {
+                usage: 3,
+                selector: 1,
+                matching_type: 1,
+                data: "d2abde240d7cd3ee".to_string(),
+            }
+        );
+        assert!(DnsRecord::from_parts(DnsRecordType::CERT, "1 12345").is_err());
+
+        for record in [
+            DnsRecord::CERT {
+                cert_type: 1,
+                key_tag: 12345,
+                algorithm: 8,
+                content: "not base64!".to_string(),
+            },
+            DnsRecord::SMIMEA {
+                usage: 3,
+                selector: 1,
+                matching_type: 1,
+                data: "d2a".to_string(),
+            },
+            DnsRecord::OPENPGPKEY {
+                content: "%%%".to_string(),
+            },
+        ] {
+            assert!(
+                matches!(record.validate(), Err(Error::Parse(_))),
+                "{record:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn mx_and_srv_from_combined_strings() {
+        assert_eq!(
+            DnsRecord::mx_from_str("10 mail.example.com").unwrap(),
+            DnsRecord::MX {
+                content: "mail.example.com".to_string(),
+                priority: 10,
+            }
+        );
+        assert_eq!(
+            DnsRecord::srv_from_str(" 10 20  443 sip.example.com ").unwrap(),
+            DnsRecord::SRV {
+                content: "sip.example.com".to_string(),
+                priority: 10,
+                weight: 20,
+                port: 443,
+            }
+        );
+
+        for value in [
+            "",
+            "mail.example.com",
+            "ten mail.example.com",
+            "10 mail example",
+        ] {
+            assert!(
+                matches!(DnsRecord::mx_from_str(value), Err(Error::Parse(_))),
+                "{value}"
+            );
+        }
+        for value in [
+            "10 20 sip.example.com",
+            "10 20 70000 sip.example.com",
+            "1 2 3 4 5",
+        ] {
+            assert!(
+                matches!(DnsRecord::srv_from_str(value), Err(Error::Parse(_))),
+                "{value}"
+            );
+        }
+    }
 }
@@ -0,0 +1,623 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    canonical_name,
+    http::{HttpClient, HttpClientBuilder, PlannedRequest, RateLimitInfo},
+    qualify_name, strip_origin_from_name, ApiCacheManager, Capabilities, Change, DnsRecord,
+    DnsRecordType, Error, IntoFqdn, MetricsCallback,
+};
+
+use super::{concat_txt, require_credential};
+
+const ENDPOINT: &str = "https://njal.la/api/1/";
+
+#[derive(Clone)]
+pub struct NjallaProvider {
+    client: HttpClientBuilder,
+    record_ids: ApiCacheManager<Value>,
+    metrics: Option<MetricsCallback>,
+}
+
+#[derive(Serialize, Debug)]
+struct RpcRequest<'a, P> {
+    method: &'a str,
+    params: P,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RecordParams<'a> {
+    pub domain: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    #[serde(rename = "type")]
+    pub record_type: &'static str,
+    pub name: &'a str,
+    pub content: String,
+    pub ttl: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prio: Option<u16>,
+}
+
+#[derive(Serialize, Debug)]
+struct NoParams {}
+
+#[derive(Serialize, Debug)]
+struct DomainParams<'a> {
+    domain: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct RemoveParams<'a> {
+    domain: &'a str,
+    id: Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct DomainList {
+    domains: Vec<DomainEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DomainEntry {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordList {
+    records: Vec<RecordEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawRecordList {
+    records: Vec<Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecordEntry {
+    pub id: Value,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: u32,
+    #[serde(default)]
+    pub prio: Option<u16>,
+}
+
+impl NjallaProvider {
+    pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+        record_types: Cow::Borrowed(DnsRecordType::BASIC),
+        supports_list: true,
+        supports_multi_value: true,
+    };
+
+    pub(crate) fn new(
+        api_token: impl AsRef<str>,
+        timeout: Option<Duration>,
+    ) -> crate::Result<Self> {
+        let client = HttpClientBuilder::default()
+            .with_header(
+                "Authorization",
+                format!("Njalla {}", require_credential(api_token.as_ref())?),
+            )
+            .with_timeout(timeout);
+
+        Ok(Self {
+            client,
+            record_ids: ApiCacheManager::default(),
+            metrics: None,
+        })
+    }
+
+    pub(crate) fn map_client(
+        mut self,
+        f: impl FnOnce(HttpClientBuilder) -> HttpClientBuilder,
+    ) -> Self {
+        self.client = f(self.client);
+        self
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        self.client.timeout()
+    }
+
+    pub(crate) fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        self.client.last_rate_limit()
+    }
+
+    pub(crate) fn with_metrics(mut self, metrics: MetricsCallback) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&MetricsCallback> {
+        self.metrics.as_ref()
+    }
+
+    fn request(&self, method: &str, params: impl Serialize) -> crate::Result<HttpClient> {
+        self.client
+            .post(ENDPOINT)
+            .with_body(RpcRequest { method, params })
+    }
+
+    /// Creates are sent as is, updates and deletes start by listing the
+    /// domain's records to find the record id.
+    pub(crate) fn describe_request<'x>(
+        &self,
+        change: &Change,
+        origin: impl IntoFqdn<'x>,
+    ) -> crate::Result<PlannedRequest> {
+        let domain = origin.into_name();
+        match change {
+            Change::Create { name, record, ttl } => self.request(
+                "add-record",
+                RecordParams::new(
+                    domain.as_ref(),
+                    &strip_origin_from_name(name, domain.as_ref()),
+                    record.clone(),
+                    *ttl,
+                ),
+            ),
+            Change::Update { .. } | Change::Delete { .. } => self.request(
+                "list-records",
+                DomainParams {
+                    domain: domain.as_ref(),
+                },
+            ),
+        }
+        .map(|request| request.plan())
+    }
+
+    async fn call<P, T>(&self, method: &str, params: P) -> crate::Result<T>
+    where
+        P: Serialize,
+        T: DeserializeOwned,
+    {
+        let response = self
+            .request(method, params)?
+            .send::<RpcResponse<T>>()
+            .await?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(Error::Api(format!(
+                "Failed to {method}: {} ({})",
+                error.message, error.code
+            ))),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(Error::Api(format!("Failed to {method}: empty response"))),
+        }
+    }
+
+    pub(crate) async fn list_zones(&self) -> crate::Result<Vec<String>> {
+        self.call::<_, DomainList>("list-domains", NoParams {})
+            .await
+            .map(|list| list.domains.into_iter().map(|domain| domain.name).collect())
+    }
+
+    async fn list_records(&self, domain: &str) -> crate::Result<Vec<RecordEntry>> {
+        self.call::<_, RecordList>("list-records", DomainParams { domain })
+            .await
+            .map(|list| list.records)
+    }
+
+    async fn obtain_record_id(
+        &self,
+        domain: &str,
+        name: &str,
+        record_type: &str,
+    ) -> crate::Result<Value> {
+        self.record_ids
+            .get_or_update_retrying(&format!("{domain}/{name}/{record_type}"), || async {
+                self.list_records(domain)
+                    .await?
+                    .into_iter()
+                    .find(|record| {
+                        canonical_name(&record.name) == name && record.record_type == record_type
+                    })
+                    .map(|record| record.id)
+                    .ok_or_else(|| Error::RecordNotFound(name.to_string()))
+            })
+            .await
+    }
+
+    pub(crate) async fn get_all(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(DnsRecord, u32)>> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+        self.list_records(domain.as_ref())
+            .await?
+            .into_iter()
+            .filter(|record| {
+                canonical_name(&record.name) == name && record.record_type == record_type.as_str()
+            })
+            .map(|record| {
+                let content = match record.prio {
+                    Some(prio) => format!("{prio} {}", record.content),
+                    None => record.content,
+                };
+                Ok((DnsRecord::from_parts(record_type, &content)?, record.ttl))
+            })
+            .collect()
+    }
+
+    pub(crate) async fn get_raw(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Value> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+        self.call::<_, RawRecordList>(
+            "list-records",
+            DomainParams {
+                domain: domain.as_ref(),
+            },
+        )
+        .await?
+        .records
+        .into_iter()
+        .find(|record| {
+            record
+                .get("name")
+                .and_then(Value::as_str)
+                .is_some_and(|record_name| canonical_name(record_name) == name)
+                && record.get("type").and_then(Value::as_str) == Some(record_type.as_str())
+        })
+        .ok_or_else(|| Error::RecordNotFound(name.clone()))
+    }
+
+    pub(crate) async fn list(
+        &self,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(String, DnsRecord, u32)>> {
+        let domain = origin.into_name();
+        Ok(self
+            .list_records(domain.as_ref())
+            .await?
+            .into_iter()
+            .filter_map(|record| {
+                let record_type = record.record_type.parse().ok()?;
+                let content = match record.prio {
+                    Some(prio) => format!("{prio} {}", record.content),
+                    None => record.content,
+                };
+                Some((
+                    qualify_name(&record.name, domain.as_ref()),
+                    DnsRecord::from_parts(record_type, &content).ok()?,
+                    record.ttl,
+                ))
+            })
+            .collect())
+    }
+
+    pub(crate) async fn create(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+
+        self.call::<_, Value>(
+            "add-record",
+            RecordParams::new(domain.as_ref(), &name, record, ttl),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub(crate) async fn update(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+        let mut params = RecordParams::new(domain.as_ref(), &name, record, ttl);
+        params.id = self
+            .obtain_record_id(domain.as_ref(), &name, params.record_type)
+            .await?
+            .into();
+
+        self.call::<_, Value>("edit-record", params)
+            .await
+            .map(|_| ())
+    }
+
+    pub(crate) async fn delete(
+        &self,
+        name: impl IntoFqdn<'_>,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+        let record = self
+            .list_records(domain.as_ref())
+            .await?
+            .into_iter()
+            .find(|record| canonical_name(&record.name) == name)
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))?;
+
+        self.record_ids.invalidate(&format!(
+            "{}/{name}/{}",
+            domain.as_ref(),
+            record.record_type
+        ));
+        self.call::<_, Value>(
+            "remove-record",
+            RemoveParams {
+                domain: domain.as_ref(),
+                id: record.id,
+            },
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub(crate) async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+        let record_ids = self
+            .list_records(domain.as_ref())
+            .await?
+            .into_iter()
+            .filter(|record| {
+                canonical_name(&record.name) == name && record.record_type == record_type.as_str()
+            })
+            .map(|record| record.id)
+            .collect::<Vec<_>>();
+        if record_ids.is_empty() {
+            return Err(Error::RecordNotFound(name));
+        }
+
+        self.record_ids.invalidate(&format!(
+            "{}/{name}/{}",
+            domain.as_ref(),
+            record_type.as_str()
+        ));
+        for id in record_ids {
+            self.call::<_, Value>(
+                "remove-record",
+                RemoveParams {
+                    domain: domain.as_ref(),
+                    id,
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: &DnsRecord,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let domain = origin.into_name();
+        let name = strip_origin_from_name(name.into_name().as_ref(), domain.as_ref());
+        let record_type = record.as_type();
+        let entry = self
+            .list_records(domain.as_ref())
+            .await?
+            .into_iter()
+            .find(|entry| {
+                let content = match entry.prio {
+                    Some(prio) => format!("{prio} {}", entry.content),
+                    None => entry.content.clone(),
+                };
+                canonical_name(&entry.name) == name
+                    && entry.record_type == record_type.as_str()
+                    && DnsRecord::from_parts(record_type, &content)
+                        .is_ok_and(|content| content.content_eq(record))
+            })
+            .ok_or_else(|| Error::RecordNotFound(name.clone()))?;
+
+        self.record_ids
+            .invalidate(&format!("{}/{name}/{}", domain.as_ref(), entry.record_type));
+        self.call::<_, Value>(
+            "remove-record",
+            RemoveParams {
+                domain: domain.as_ref(),
+                id: entry.id,
+            },
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+impl<'a> RecordParams<'a> {
+    fn new(domain: &'a str, name: &'a str, record: DnsRecord, ttl: u32) -> Self {
+        let record_type = record.as_type().as_str();
+        let prio = record.priority();
+        let content = match record {
+            DnsRecord::A { content } => content.to_string(),
+            DnsRecord::AAAA { content } => content.to_string(),
+            DnsRecord::CNAME { content } => content,
+            DnsRecord::NS { content } => content,
+            DnsRecord::MX { content, .. } => content,
+            DnsRecord::TXT { content } => concat_txt(content),
+            DnsRecord::SRV {
+                content,
+                weight,
+                port,
+                ..
+            } => format!("{weight} {port} {content}"),
+            DnsRecord::CAA { flags, tag, value } => format!("{flags} {tag} \"{value}\""),
+            DnsRecord::CERT {
+                cert_type,
+                key_tag,
+                algorithm,
+                content,
+            } => format!("{cert_type} {key_tag} {algorithm} {content}"),
+            DnsRecord::SMIMEA {
+                usage,
+                selector,
+                matching_type,
+                data,
+            } => format!("{usage} {selector} {matching_type} {data}"),
+            DnsRecord::OPENPGPKEY { content } => content,
+            DnsRecord::SPF { content } => concat_txt(content),
+        };
+
+        Self {
+            domain,
+            id: None,
+            record_type,
+            name,
+            content,
+            ttl,
+            prio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::NjallaProvider;
+    use crate::{
+        http::tests::{MockResponse, MockServer},
+        DnsRecord,
+    };
+
+    fn provider(server: &MockServer) -> NjallaProvider {
+        NjallaProvider::new("token", None)
+            .unwrap()
+            .map_client(|client| client.with_base_url(&server.url))
+    }
+
+    #[tokio::test]
+    async fn add_record() {
+        let server = MockServer::start(vec![MockResponse::Json(
+            200,
+            json!({ "jsonrpc": "2.0", "result": { "id": 1 } }),
+        )])
+        .await;
+
+        provider(&server)
+            .create(
+                "example.com",
+                DnsRecord::MX {
+                    content: "mail.example.com".to_string(),
+                    priority: 10,
+                },
+                3600,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/api/1/");
+        assert_eq!(requests[0].header("authorization"), Some("Njalla token"));
+        assert_eq!(
+            requests[0].json(),
+            json!({
+                "method": "add-record",
+                "params": {
+                    "domain": "example.com",
+                    "type": "MX",
+                    "name": "@",
+                    "content": "mail.example.com",
+                    "ttl": 3600,
+                    "prio": 10,
+                },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn list_then_remove_record() {
+        let server = MockServer::start(vec![
+            MockResponse::Json(
+                200,
+                json!({ "result": { "records": [
+                    { "id": 1, "name": "@", "type": "A", "content": "192.0.2.1", "ttl": 300 },
+                    { "id": 2, "name": "www", "type": "A", "content": "192.0.2.2", "ttl": 300 },
+                ] } }),
+            ),
+            MockResponse::Json(200, json!({ "result": {} })),
+        ])
+        .await;
+
+        provider(&server)
+            .delete("www.example.com", "example.com")
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].json(),
+            json!({ "method": "list-records", "params": { "domain": "example.com" } })
+        );
+        assert_eq!(
+            requests[1].json(),
+            json!({ "method": "remove-record", "params": { "domain": "example.com", "id": 2 } })
+        );
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_are_reported() {
+        let server = MockServer::start(vec![MockResponse::Json(
+            200,
+            json!({ "error": { "code": 403, "message": "Permission denied" } }),
+        )])
+        .await;
+
+        let err = provider(&server)
+            .delete("www.example.com", "example.com")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "API error: Failed to list-records: Permission denied (403)"
+        );
+    }
+}
@@ -9,28 +9,59 @@
  * except according to those terms.
  */
 
-use std::net::{AddrParseError, SocketAddr};
+use std::borrow::Cow;
+use std::net::{AddrParseError, SocketAddr, SocketAddrV6};
 use std::sync::Arc;
+use std::time::Duration;
 
+use base64::{engine::general_purpose::STANDARD, Engine};
 use hickory_client::client::{AsyncClient, ClientConnection, ClientHandle, Signer};
 use hickory_client::error::ClientError;
 use hickory_client::op::ResponseCode;
 use hickory_client::proto::error::ProtoError;
 use hickory_client::proto::rr::dnssec::tsig::TSigner;
 use hickory_client::proto::rr::dnssec::{Algorithm, KeyPair, Private, SigSigner};
+use hickory_client::rr::rdata::caa::{read_iodef, read_issuer};
 use hickory_client::rr::rdata::key::KEY;
 use hickory_client::rr::rdata::tsig::TsigAlgorithm;
-use hickory_client::rr::rdata::{A, AAAA, CNAME, MX, NS, SRV, TXT};
-use hickory_client::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_client::rr::rdata::{A, AAAA, CAA, CNAME, MX, NS, NULL, OPENPGPKEY, SOA, SRV, TXT};
+use hickory_client::rr::{DNSClass, Name, RData, Record, RecordSet, RecordType};
 use hickory_client::tcp::TcpClientConnection;
 use hickory_client::udp::UdpClientConnection;
 
-use crate::{DnsRecord, Error, IntoFqdn};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::{
+    Capabilities, DnsRecord, DnsRecordType, Error, IntoFqdn, MetricsCallback, SoaParams, Target,
+};
+
+use super::{
+    base64_decode, hex_decode, hex_encode, join_txt_strings, require_credential, txt_strings,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+// Record types without structured support in hickory, sent as raw rdata.
+const CERT_TYPE: RecordType = RecordType::Unknown(37);
+const SMIMEA_TYPE: RecordType = RecordType::Unknown(53);
+const SPF_TYPE: RecordType = RecordType::Unknown(99);
 
 #[derive(Clone)]
 pub struct Rfc2136Provider {
     addr: DnsAddress,
+    bind_addr: Option<SocketAddr>,
     signer: Arc<Signer>,
+    metrics: Option<MetricsCallback>,
+    connection: Arc<Mutex<Option<Connection>>>,
+    dns_class: DNSClass,
+}
+
+/// A connected client reused across operations, along with the task driving
+/// it. The task is aborted when the connection is dropped.
+struct Connection {
+    client: AsyncClient,
+    background: JoinHandle<Result<(), ProtoError>>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -40,22 +71,37 @@ pub enum DnsAddress {
 }
 
 impl Rfc2136Provider {
+    pub(crate) const CAPABILITIES: Capabilities = Capabilities {
+        record_types: Cow::Borrowed(DnsRecordType::ALL),
+        supports_list: false,
+        supports_multi_value: true,
+    };
+
     pub(crate) fn new_tsig(
         addr: impl TryInto<DnsAddress>,
         key_name: impl AsRef<str>,
         key: impl Into<Vec<u8>>,
         algorithm: TsigAlgorithm,
     ) -> crate::Result<Self> {
+        let key = key.into();
+        if key.is_empty() {
+            return Err(Error::Config("empty credential".to_string()));
+        }
+
         Ok(Rfc2136Provider {
             addr: addr
                 .try_into()
-                .map_err(|_| Error::Parse("Invalid address".to_string()))?,
+                .map_err(|_| Error::Config("Invalid address".to_string()))?,
+            bind_addr: None,
             signer: Arc::new(Signer::from(TSigner::new(
-                key.into(),
+                key,
                 algorithm,
-                Name::from_ascii(key_name.as_ref())?,
+                Name::from_ascii(require_credential(key_name.as_ref())?)?,
                 60,
             )?)),
+            metrics: None,
+            connection: Arc::new(Mutex::new(None)),
+            dns_class: DNSClass::IN,
         })
     }
 
@@ -80,26 +126,149 @@ impl Rfc2136Provider {
         Ok(Rfc2136Provider {
             addr: addr
                 .try_into()
-                .map_err(|_| Error::Parse("Invalid address".to_string()))?,
+                .map_err(|_| Error::Config("Invalid address".to_string()))?,
+            bind_addr: None,
             signer: Arc::new(Signer::from(signer)),
+            metrics: None,
+            connection: Arc::new(Mutex::new(None)),
+            dns_class: DNSClass::IN,
         })
     }
 
+    pub(crate) fn with_bind_addr(mut self, bind_addr: Option<SocketAddr>) -> Self {
+        self.bind_addr = bind_addr;
+        self
+    }
+
+    pub(crate) fn with_dns_class(mut self, dns_class: DNSClass) -> Self {
+        self.dns_class = dns_class;
+        self
+    }
+
+    pub(crate) fn with_metrics(mut self, metrics: MetricsCallback) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&MetricsCallback> {
+        self.metrics.as_ref()
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        TIMEOUT
+    }
+
+    fn record(&self, name: Name, rr_type: RecordType, ttl: u32) -> Record {
+        let mut record = Record::with(name, rr_type, ttl);
+        record.set_dns_class(self.dns_class);
+        record
+    }
+
     async fn connect(&self) -> crate::Result<AsyncClient> {
-        match &self.addr {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection
+            .as_ref()
+            .filter(|connection| !connection.background.is_finished())
+        {
+            return Ok(connection.client.clone());
+        }
+
+        let (client, background) = match &self.addr {
             DnsAddress::Udp(addr) => {
-                let conn = UdpClientConnection::new(*addr)?.new_stream(Some(self.signer.clone()));
+                let conn = UdpClientConnection::with_bind_addr_and_timeout(
+                    *addr,
+                    self.bind_addr,
+                    TIMEOUT,
+                )?
+                .new_stream(Some(self.signer.clone()));
                 let (client, bg) = AsyncClient::connect(conn).await?;
-                tokio::spawn(bg);
-                Ok(client)
+                (client, tokio::spawn(bg))
             }
             DnsAddress::Tcp(addr) => {
-                let conn = TcpClientConnection::new(*addr)?.new_stream(Some(self.signer.clone()));
+                let conn = TcpClientConnection::with_bind_addr_and_timeout(
+                    *addr,
+                    self.bind_addr,
+                    TIMEOUT,
+                )?
+                .new_stream(Some(self.signer.clone()));
                 let (client, bg) = AsyncClient::connect(conn).await?;
-                tokio::spawn(bg);
-                Ok(client)
+                (client, tokio::spawn(bg))
             }
+        };
+        *connection = Some(Connection {
+            client: client.clone(),
+            background,
+        });
+        Ok(client)
+    }
+
+    /// Drop the cached connection after a client error, the next operation
+    /// reconnects.
+    fn disconnect(&self, err: ClientError) -> Error {
+        if let Ok(mut connection) = self.connection.try_lock() {
+            connection.take();
         }
+        err.into()
+    }
+
+    pub(crate) async fn get_all(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Vec<(DnsRecord, u32)>> {
+        let name = Name::from_str_relaxed(name.into_fqdn().as_ref())?;
+        let mut client = self.connect().await?;
+        let result = client
+            .query(name.clone(), self.dns_class, record_type.into())
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() != ResponseCode::NoError {
+            return Err(crate::Error::Response(result.response_code().to_string()));
+        }
+
+        Ok(result
+            .answers()
+            .iter()
+            .filter(|record| record.record_type() == RecordType::from(record_type))
+            .filter_map(|record| {
+                record
+                    .data()
+                    .and_then(convert_rdata)
+                    .map(|content| (content, record.ttl()))
+            })
+            .collect())
+    }
+
+    pub(crate) async fn get_raw(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        _origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<Value> {
+        let name = Name::from_str_relaxed(name.into_fqdn().as_ref())?;
+        let mut client = self.connect().await?;
+        let result = client
+            .query(name.clone(), self.dns_class, record_type.into())
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() != ResponseCode::NoError {
+            return Err(crate::Error::Response(result.response_code().to_string()));
+        }
+
+        result
+            .answers()
+            .iter()
+            .find(|record| record.record_type() == RecordType::from(record_type))
+            .map(|record| {
+                json!({
+                    "name": record.name().to_utf8(),
+                    "type": record.record_type().to_string(),
+                    "ttl": record.ttl(),
+                    "data": record.data().map(ToString::to_string),
+                })
+            })
+            .ok_or_else(|| Error::RecordNotFound(name.to_utf8()))
     }
 
     pub(crate) async fn create(
@@ -110,7 +279,7 @@ impl Rfc2136Provider {
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
         let (rr_type, rdata) = convert_record(record)?;
-        let mut record = Record::with(
+        let mut record = self.record(
             Name::from_str_relaxed(name.into_name().as_ref())?,
             rr_type,
             ttl,
@@ -120,7 +289,8 @@ impl Rfc2136Provider {
         let mut client = self.connect().await?;
         let result = client
             .create(record, Name::from_str_relaxed(origin.into_fqdn().as_ref())?)
-            .await?;
+            .await
+            .map_err(|err| self.disconnect(err))?;
         if result.response_code() == ResponseCode::NoError {
             Ok(())
         } else {
@@ -136,7 +306,7 @@ impl Rfc2136Provider {
         origin: impl IntoFqdn<'_>,
     ) -> crate::Result<()> {
         let (rr_type, rdata) = convert_record(record)?;
-        let mut record = Record::with(
+        let mut record = self.record(
             Name::from_str_relaxed(name.into_name().as_ref())?,
             rr_type,
             ttl,
@@ -150,7 +320,135 @@ impl Rfc2136Provider {
                 Name::from_str_relaxed(origin.into_fqdn().as_ref())?,
                 false,
             )
-            .await?;
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() == ResponseCode::NoError {
+            Ok(())
+        } else {
+            Err(crate::Error::Response(result.response_code().to_string()))
+        }
+    }
+
+    /// Replace the `current` records at `name` with `record` in a single
+    /// update, which the server only applies if it still holds `current`.
+    pub(crate) async fn replace(
+        &self,
+        name: impl IntoFqdn<'_>,
+        current: Vec<DnsRecord>,
+        record: DnsRecord,
+        ttl: u32,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let name = Name::from_str_relaxed(name.into_name().as_ref())?;
+        let (rr_type, rdata) = convert_record(record)?;
+        let mut record = self.record(name.clone(), rr_type, ttl);
+        record.set_data(Some(rdata));
+
+        let mut rrset = RecordSet::new(&name, rr_type, 0);
+        rrset.set_dns_class(self.dns_class);
+        for current in current {
+            let (_, rdata) = convert_record(current)?;
+            let mut current = self.record(name.clone(), rr_type, ttl);
+            current.set_data(Some(rdata));
+            rrset.insert(current, 0);
+        }
+
+        let mut client = self.connect().await?;
+        let result = client
+            .compare_and_swap(
+                rrset,
+                record,
+                Name::from_str_relaxed(origin.into_fqdn().as_ref())?,
+            )
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() == ResponseCode::NoError {
+            Ok(())
+        } else {
+            Err(crate::Error::Response(result.response_code().to_string()))
+        }
+    }
+
+    pub(crate) async fn set_zone_soa(
+        &self,
+        origin: impl IntoFqdn<'_>,
+        params: SoaParams,
+    ) -> crate::Result<()> {
+        let origin = Name::from_str_relaxed(origin.into_fqdn().as_ref())?;
+        let mut client = self.connect().await?;
+        let result = client
+            .query(origin.clone(), self.dns_class, RecordType::SOA)
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() != ResponseCode::NoError {
+            return Err(crate::Error::Response(result.response_code().to_string()));
+        }
+        let (soa, ttl) = result
+            .answers()
+            .iter()
+            .find_map(|record| match record.data() {
+                Some(RData::SOA(soa)) => Some((soa.clone(), record.ttl())),
+                _ => None,
+            })
+            .ok_or_else(|| Error::ZoneNotFound(origin.to_string()))?;
+
+        let mut record = self.record(origin.clone(), RecordType::SOA, ttl);
+        record.set_data(Some(RData::SOA(updated_soa(&soa, &params))));
+
+        let result = client
+            .append(record, origin, false)
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() == ResponseCode::NoError {
+            Ok(())
+        } else {
+            Err(crate::Error::Response(result.response_code().to_string()))
+        }
+    }
+
+    pub(crate) async fn delete_rrset(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record_type: DnsRecordType,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let record = self.record(
+            Name::from_str_relaxed(name.into_name().as_ref())?,
+            record_type.into(),
+            0,
+        );
+
+        let mut client = self.connect().await?;
+        let result = client
+            .delete_rrset(record, Name::from_str_relaxed(origin.into_fqdn().as_ref())?)
+            .await
+            .map_err(|err| self.disconnect(err))?;
+        if result.response_code() == ResponseCode::NoError {
+            Ok(())
+        } else {
+            Err(crate::Error::Response(result.response_code().to_string()))
+        }
+    }
+
+    pub(crate) async fn delete_value(
+        &self,
+        name: impl IntoFqdn<'_>,
+        record: DnsRecord,
+        origin: impl IntoFqdn<'_>,
+    ) -> crate::Result<()> {
+        let (rr_type, rdata) = convert_record(record)?;
+        let mut record = self.record(
+            Name::from_str_relaxed(name.into_name().as_ref())?,
+            rr_type,
+            0,
+        );
+        record.set_data(Some(rdata));
+
+        let mut client = self.connect().await?;
+        let result = client
+            .delete_by_rdata(record, Name::from_str_relaxed(origin.into_fqdn().as_ref())?)
+            .await
+            .map_err(|err| self.disconnect(err))?;
         if result.response_code() == ResponseCode::NoError {
             Ok(())
         } else {
@@ -168,9 +466,10 @@ impl Rfc2136Provider {
             .delete_all(
                 Name::from_str_relaxed(name.into_name().as_ref())?,
                 Name::from_str_relaxed(origin.into_fqdn().as_ref())?,
-                DNSClass::IN,
+                self.dns_class,
             )
-            .await?;
+            .await
+            .map_err(|err| self.disconnect(err))?;
         if result.response_code() == ResponseCode::NoError {
             Ok(())
         } else {
@@ -179,23 +478,45 @@ impl Rfc2136Provider {
     }
 }
 
+/// The SOA with the timers in `params` applied. The serial is bumped so that
+/// secondaries pick up the change.
+fn updated_soa(soa: &SOA, params: &SoaParams) -> SOA {
+    let timer = |value: Option<u32>, current: i32| {
+        value.map_or(current, |value| i32::try_from(value).unwrap_or(i32::MAX))
+    };
+    SOA::new(
+        soa.mname().clone(),
+        soa.rname().clone(),
+        soa.serial().wrapping_add(1),
+        timer(params.refresh, soa.refresh()),
+        timer(params.retry, soa.retry()),
+        timer(params.expire, soa.expire()),
+        params.minimum.unwrap_or(soa.minimum()),
+    )
+}
+
+/// Parse the target of a CNAME, MX, NS or SRV record, anchoring it at the
+/// root when it is fully qualified. See [`Target`].
+fn target_name(content: &str) -> crate::Result<Name> {
+    Ok(Name::from_str_relaxed(Target::from(content).to_string())?)
+}
+
 fn convert_record(record: DnsRecord) -> crate::Result<(RecordType, RData)> {
     Ok(match record {
         DnsRecord::A { content } => (RecordType::A, RData::A(A::from(content))),
         DnsRecord::AAAA { content } => (RecordType::AAAA, RData::AAAA(AAAA::from(content))),
         DnsRecord::CNAME { content } => (
             RecordType::CNAME,
-            RData::CNAME(CNAME(Name::from_str_relaxed(content)?)),
-        ),
-        DnsRecord::NS { content } => (
-            RecordType::NS,
-            RData::NS(NS(Name::from_str_relaxed(content)?)),
+            RData::CNAME(CNAME(target_name(&content)?)),
         ),
+        DnsRecord::NS { content } => (RecordType::NS, RData::NS(NS(target_name(&content)?))),
         DnsRecord::MX { content, priority } => (
             RecordType::MX,
-            RData::MX(MX::new(priority, Name::from_str_relaxed(content)?)),
+            RData::MX(MX::new(priority, target_name(&content)?)),
         ),
-        DnsRecord::TXT { content } => (RecordType::TXT, RData::TXT(TXT::new(vec![content]))),
+        DnsRecord::TXT { content } => {
+            (RecordType::TXT, RData::TXT(TXT::new(txt_strings(&content))))
+        }
         DnsRecord::SRV {
             content,
             priority,
@@ -203,16 +524,164 @@ fn convert_record(record: DnsRecord) -> crate::Result<(RecordType, RData)> {
             port,
         } => (
             RecordType::SRV,
-            RData::SRV(SRV::new(
-                priority,
-                weight,
-                port,
-                Name::from_str_relaxed(content)?,
-            )),
+            RData::SRV(SRV::new(priority, weight, port, target_name(&content)?)),
+        ),
+        DnsRecord::CAA { flags, tag, value } => {
+            let critical = flags & 0x80 != 0;
+            let caa = match tag.as_str() {
+                "issue" | "issuewild" => {
+                    let (name, options) = read_issuer(value.as_bytes())?;
+                    if tag == "issue" {
+                        CAA::new_issue(critical, name, options)
+                    } else {
+                        CAA::new_issuewild(critical, name, options)
+                    }
+                }
+                "iodef" => CAA::new_iodef(critical, read_iodef(value.as_bytes())?),
+                _ => {
+                    return Err(Error::Parse(format!("Unsupported CAA tag {tag}")));
+                }
+            };
+            (RecordType::CAA, RData::CAA(caa))
+        }
+        DnsRecord::CERT {
+            cert_type,
+            key_tag,
+            algorithm,
+            content,
+        } => {
+            let mut rdata = Vec::with_capacity(5 + content.len());
+            rdata.extend_from_slice(&cert_type.to_be_bytes());
+            rdata.extend_from_slice(&key_tag.to_be_bytes());
+            rdata.push(algorithm);
+            rdata.extend(base64_decode(&content)?);
+            (
+                CERT_TYPE,
+                RData::Unknown {
+                    code: CERT_TYPE,
+                    rdata: NULL::with(rdata),
+                },
+            )
+        }
+        DnsRecord::SMIMEA {
+            usage,
+            selector,
+            matching_type,
+            data,
+        } => {
+            let mut rdata = vec![usage, selector, matching_type];
+            rdata.extend(hex_decode(&data)?);
+            (
+                SMIMEA_TYPE,
+                RData::Unknown {
+                    code: SMIMEA_TYPE,
+                    rdata: NULL::with(rdata),
+                },
+            )
+        }
+        DnsRecord::OPENPGPKEY { content } => (
+            RecordType::OPENPGPKEY,
+            RData::OPENPGPKEY(OPENPGPKEY::new(base64_decode(&content)?)),
         ),
+        DnsRecord::SPF { content } => {
+            // Same wire format as TXT: length-prefixed character-strings.
+            let mut rdata = Vec::with_capacity(content.len() + 1);
+            for string in txt_strings(&content) {
+                if string.is_empty() {
+                    rdata.push(0);
+                }
+                for chunk in string.as_bytes().chunks(255) {
+                    rdata.push(chunk.len() as u8);
+                    rdata.extend_from_slice(chunk);
+                }
+            }
+            (
+                SPF_TYPE,
+                RData::Unknown {
+                    code: SPF_TYPE,
+                    rdata: NULL::with(rdata),
+                },
+            )
+        }
+    })
+}
+
+pub(crate) fn convert_rdata(rdata: &RData) -> Option<DnsRecord> {
+    Some(match rdata {
+        RData::A(content) => DnsRecord::A { content: content.0 },
+        RData::AAAA(content) => DnsRecord::AAAA { content: content.0 },
+        RData::CNAME(content) => DnsRecord::CNAME {
+            content: content.0.to_utf8(),
+        },
+        RData::NS(content) => DnsRecord::NS {
+            content: content.0.to_utf8(),
+        },
+        RData::MX(content) => DnsRecord::MX {
+            content: content.exchange().to_utf8(),
+            priority: content.preference(),
+        },
+        RData::TXT(content) => DnsRecord::TXT {
+            content: join_txt_strings(
+                &content
+                    .iter()
+                    .map(|data| String::from_utf8_lossy(data))
+                    .collect::<Vec<_>>(),
+            ),
+        },
+        RData::SRV(content) => DnsRecord::SRV {
+            content: content.target().to_utf8(),
+            priority: content.priority(),
+            weight: content.weight(),
+            port: content.port(),
+        },
+        RData::CAA(content) => DnsRecord::CAA {
+            flags: if content.issuer_critical() { 0x80 } else { 0 },
+            tag: content.tag().to_string(),
+            value: content.value().to_string().trim_matches('"').to_string(),
+        },
+        RData::OPENPGPKEY(content) => DnsRecord::OPENPGPKEY {
+            content: STANDARD.encode(content.public_key()),
+        },
+        RData::Unknown { code, rdata } if *code == CERT_TYPE => match rdata.anything() {
+            [t0, t1, k0, k1, algorithm, certificate @ ..] => DnsRecord::CERT {
+                cert_type: u16::from_be_bytes([*t0, *t1]),
+                key_tag: u16::from_be_bytes([*k0, *k1]),
+                algorithm: *algorithm,
+                content: STANDARD.encode(certificate),
+            },
+            _ => return None,
+        },
+        RData::Unknown { code, rdata } if *code == SMIMEA_TYPE => match rdata.anything() {
+            [usage, selector, matching_type, data @ ..] => DnsRecord::SMIMEA {
+                usage: *usage,
+                selector: *selector,
+                matching_type: *matching_type,
+                data: hex_encode(data),
+            },
+            _ => return None,
+        },
+        RData::Unknown { code, rdata } if *code == SPF_TYPE => {
+            let mut strings = Vec::new();
+            let mut data = rdata.anything();
+            while let [len, rest @ ..] = data {
+                let string = rest.get(..*len as usize)?;
+                strings.push(String::from_utf8_lossy(string));
+                data = &rest[*len as usize..];
+            }
+            DnsRecord::SPF {
+                content: join_txt_strings(&strings),
+            }
+        }
+        _ => return None,
     })
 }
 
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.background.abort();
+    }
+}
+
 impl TryFrom<&str> for DnsAddress {
     type Error = ();
 
@@ -240,7 +709,18 @@ impl TryFrom<&str> for DnsAddress {
             (host, "53")
         };
 
-        let addr = SocketAddr::new(host.parse().map_err(|_| ())?, port.parse().map_err(|_| ())?);
+        let port = port.parse().map_err(|_| ())?;
+        // Link-local IPv6 addresses may carry a numeric zone id, as in `[fe80::1%2]:53`.
+        // Interface names are not resolved.
+        let addr = match host.split_once('%') {
+            Some((host, scope_id)) => SocketAddr::V6(SocketAddrV6::new(
+                host.parse().map_err(|_| ())?,
+                port,
+                0,
+                scope_id.parse().map_err(|_| ())?,
+            )),
+            None => SocketAddr::new(host.parse().map_err(|_| ())?, port),
+        };
 
         if is_tcp {
             Ok(DnsAddress::Tcp(addr))
@@ -266,6 +746,25 @@ impl TryFrom<String> for DnsAddress {
     }
 }
 
+impl From<DnsRecordType> for RecordType {
+    fn from(record_type: DnsRecordType) -> Self {
+        match record_type {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::AAAA => RecordType::AAAA,
+            DnsRecordType::CNAME => RecordType::CNAME,
+            DnsRecordType::NS => RecordType::NS,
+            DnsRecordType::MX => RecordType::MX,
+            DnsRecordType::TXT => RecordType::TXT,
+            DnsRecordType::SRV => RecordType::SRV,
+            DnsRecordType::CAA => RecordType::CAA,
+            DnsRecordType::CERT => CERT_TYPE,
+            DnsRecordType::SMIMEA => SMIMEA_TYPE,
+            DnsRecordType::OPENPGPKEY => RecordType::OPENPGPKEY,
+            DnsRecordType::SPF => SPF_TYPE,
+        }
+    }
+}
+
 impl From<crate::TsigAlgorithm> for TsigAlgorithm {
     fn from(alg: crate::TsigAlgorithm) -> Self {
         match alg {
@@ -312,3 +811,446 @@ impl From<ClientError> for Error {
         Error::Client(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        str::FromStr,
+        sync::{Arc, Mutex},
+    };
+
+    use hickory_client::op::{Message, MessageType, OpCode, ResponseCode, UpdateMessage};
+    use hickory_client::proto::rr::dnssec::tsig::TSigner;
+    use hickory_client::proto::serialize::binary::{BinDecodable, BinEncodable, BinEncoder};
+    use hickory_client::rr::rdata::tsig::{self, make_tsig_record, TSIG};
+    use hickory_client::rr::{rdata::SOA, DNSClass, Name, RData, Record, RecordType};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::{convert_rdata, convert_record, updated_soa, DnsAddress, Rfc2136Provider, Signer};
+    use crate::{DnsRecord, DnsRecordType, DnsUpdater, Error, SoaParams, TsigAlgorithm};
+
+    /// Messages received by [`update_server`], along with the index of the
+    /// connection they arrived on.
+    type Received = Arc<Mutex<Vec<(usize, Message)>>>;
+
+    fn signer() -> TSigner {
+        TSigner::new(
+            b"secret".to_vec(),
+            tsig::TsigAlgorithm::HmacSha256,
+            Name::from_ascii("key").unwrap(),
+            60,
+        )
+        .unwrap()
+    }
+
+    fn provider(addr: SocketAddr) -> Rfc2136Provider {
+        Rfc2136Provider::new_tsig(
+            DnsAddress::Tcp(addr),
+            "key",
+            b"secret".to_vec(),
+            tsig::TsigAlgorithm::HmacSha256,
+        )
+        .unwrap()
+    }
+
+    /// Answer an unsigned query with NXDOMAIN.
+    fn nxdomain_response(request: &[u8]) -> (Message, Vec<u8>) {
+        let request = Message::from_vec(request).unwrap();
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(request.op_code())
+            .set_response_code(ResponseCode::NXDomain)
+            .add_queries(request.queries().to_vec());
+        let response = response.to_bytes().unwrap();
+
+        (request, response)
+    }
+
+    /// Answer a TSIG signed request with a signed NoError response.
+    fn signed_response(signer: &TSigner, request: &[u8]) -> (Message, Vec<u8>) {
+        let (request_mac, _, time) = signer.verify_message_byte(None, request, true).unwrap();
+        let request = Message::from_vec(request).unwrap();
+
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(request.op_code())
+            .set_response_code(ResponseCode::NoError)
+            .add_queries(request.queries().to_vec());
+
+        // The response MAC covers the request MAC, the unsigned response and
+        // the TSIG variables.
+        let pre_tsig = TSIG::new(
+            tsig::TsigAlgorithm::HmacSha256,
+            time,
+            signer.fudge(),
+            Vec::new(),
+            response.id(),
+            0,
+            Vec::new(),
+        );
+        let mut tbs = (request_mac.len() as u16).to_be_bytes().to_vec();
+        tbs.extend_from_slice(&request_mac);
+        tbs.extend_from_slice(&response.to_bytes().unwrap());
+        let mut variables = Vec::new();
+        pre_tsig
+            .emit_tsig_for_mac(&mut BinEncoder::new(&mut variables), signer.signer_name())
+            .unwrap();
+        tbs.extend_from_slice(&variables);
+        let mac = signer.sign(&tbs).unwrap();
+        response.add_tsig(make_tsig_record(
+            signer.signer_name().clone(),
+            pre_tsig.set_mac(mac),
+        ));
+
+        (request, response.to_bytes().unwrap())
+    }
+
+    /// A TCP server accepting RFC 2136 updates signed with [`signer`]. Queries
+    /// are answered with NXDOMAIN, as no names are known.
+    async fn update_server() -> (SocketAddr, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = Received::default();
+
+        tokio::spawn({
+            let received = received.clone();
+            async move {
+                let mut connection = 0;
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let received = received.clone();
+                    tokio::spawn(async move {
+                        let signer = signer();
+                        while let Ok(len) = stream.read_u16().await {
+                            let mut request = vec![0u8; len as usize];
+                            if stream.read_exact(&mut request).await.is_err() {
+                                break;
+                            }
+                            let (request, response) = if Message::from_vec(&request)
+                                .unwrap()
+                                .op_code()
+                                == OpCode::Query
+                            {
+                                nxdomain_response(&request)
+                            } else {
+                                signed_response(&signer, &request)
+                            };
+                            received.lock().unwrap().push((connection, request));
+                            let _ = stream.write_u16(response.len() as u16).await;
+                            let _ = stream.write_all(&response).await;
+                        }
+                    });
+                    connection += 1;
+                }
+            }
+        });
+
+        (addr, received)
+    }
+
+    #[test]
+    fn soa_minimum_is_updated() {
+        let soa = SOA::new(
+            Name::from_str("ns1.example.com.").unwrap(),
+            Name::from_str("hostmaster.example.com.").unwrap(),
+            2024010101,
+            7200,
+            3600,
+            1209600,
+            3600,
+        );
+        let params = SoaParams {
+            minimum: Some(300),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            updated_soa(&soa, &params).to_string(),
+            "ns1.example.com. hostmaster.example.com. 2024010102 7200 3600 1209600 300"
+        );
+    }
+
+    #[test]
+    fn txt_strings_are_sent_separately() {
+        let record = DnsRecord::txt_from_strings(&["first", "second"]);
+        let (record_type, rdata) = convert_record(record.clone()).unwrap();
+
+        assert_eq!(record_type, RecordType::TXT);
+        let RData::TXT(txt) = &rdata else {
+            unreachable!()
+        };
+        assert_eq!(
+            txt.iter().map(|string| string.as_ref()).collect::<Vec<_>>(),
+            [b"first".as_slice(), b"second".as_slice()]
+        );
+        assert_eq!(convert_rdata(&rdata), Some(record));
+    }
+
+    #[test]
+    fn bind_address_is_kept() {
+        let bind_addr = "127.0.0.1:0".parse().unwrap();
+        let updater = DnsUpdater::new_rfc2136_tsig_with_bind(
+            "tcp://192.0.2.53:53",
+            "key",
+            b"secret".to_vec(),
+            TsigAlgorithm::HmacSha256,
+            bind_addr,
+        )
+        .unwrap();
+        let DnsUpdater::Rfc2136(provider) = updater else {
+            unreachable!()
+        };
+        assert_eq!(provider.bind_addr, Some(bind_addr));
+
+        assert!(DnsUpdater::new_rfc2136_tsig_with_bind(
+            "ftp://192.0.2.53:53",
+            "key",
+            b"secret".to_vec(),
+            TsigAlgorithm::HmacSha256,
+            bind_addr,
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn connection_is_reused_across_operations() {
+        let (addr, received) = update_server().await;
+        let provider = provider(addr);
+        let txt = DnsRecord::TXT {
+            content: "token".to_string(),
+        };
+
+        provider
+            .create(
+                "_acme-challenge.example.com",
+                txt.clone(),
+                60,
+                "example.com",
+            )
+            .await
+            .unwrap();
+        provider
+            .clone()
+            .delete_value("_acme-challenge.example.com", txt, "example.com")
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|(connection, _)| *connection == 0));
+    }
+
+    #[tokio::test]
+    async fn delete_rrset_only_deletes_the_given_type() {
+        let (addr, received) = update_server().await;
+
+        provider(addr)
+            .delete_rrset("www.example.com", DnsRecordType::TXT, "example.com")
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let updates = received[0].1.updates();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].name().to_ascii(), "www.example.com.");
+        assert_eq!(updates[0].record_type(), RecordType::TXT);
+        assert_eq!(updates[0].dns_class(), DNSClass::ANY);
+    }
+
+    #[tokio::test]
+    async fn upsert_creates_names_that_do_not_exist() {
+        let (addr, received) = update_server().await;
+
+        DnsUpdater::Rfc2136(provider(addr))
+            .upsert(
+                "new.example.com",
+                DnsRecord::A {
+                    content: "192.0.2.1".parse().unwrap(),
+                },
+                300,
+                "example.com",
+            )
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].1.op_code(), OpCode::Query);
+        let updates = received[1].1.updates();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].name().to_ascii(), "new.example.com.");
+        assert_eq!(updates[0].record_type(), RecordType::A);
+    }
+
+    #[tokio::test]
+    async fn create_if_absent_creates_names_that_do_not_exist() {
+        let (addr, received) = update_server().await;
+
+        assert!(DnsUpdater::Rfc2136(provider(addr))
+            .create_if_absent(
+                "new.example.com",
+                DnsRecord::TXT {
+                    content: "token".to_string(),
+                },
+                300,
+                "example.com",
+            )
+            .await
+            .unwrap());
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].1.updates()[0].record_type(), RecordType::TXT);
+    }
+
+    #[test]
+    fn certificate_records_round_trip() {
+        for record in [
+            DnsRecord::CERT {
+                cert_type: 1,
+                key_tag: 12345,
+                algorithm: 8,
+                content: "MIIBCgKCAQEA".to_string(),
+            },
+            DnsRecord::SMIMEA {
+                usage: 3,
+                selector: 1,
+                matching_type: 1,
+                data: "d2abde240d7cd3ee6b4b28c54df034b9".to_string(),
+            },
+            DnsRecord::OPENPGPKEY {
+                content: "mQINBFit2jsBEADrbl5vjVxYeAE0g0IDYCBpHirv1Sjl".to_string(),
+            },
+        ] {
+            let (record_type, rdata) = convert_record(record.clone()).unwrap();
+            let wire = Record::from_rdata(
+                Name::from_str("c93f1e400f26708f98cb19d936620da35eec8f72e57f9eec01c1afd6._smimecert.example.com.")
+                    .unwrap(),
+                300,
+                rdata,
+            )
+            .to_bytes()
+            .unwrap();
+            let decoded = Record::from_bytes(&wire).unwrap();
+
+            assert_eq!(decoded.record_type(), record_type);
+            assert_eq!(decoded.data().and_then(convert_rdata), Some(record));
+        }
+    }
+
+    #[test]
+    fn socket_addresses_are_accepted_directly() {
+        let addr: SocketAddr = "192.0.2.53:5353".parse().unwrap();
+        let updater = DnsUpdater::new_rfc2136_tsig(
+            DnsAddress::Tcp(addr),
+            "key",
+            b"secret".to_vec(),
+            TsigAlgorithm::HmacSha256,
+        )
+        .unwrap();
+        let DnsUpdater::Rfc2136(provider) = updater else {
+            unreachable!()
+        };
+        assert_eq!(provider.addr, DnsAddress::Tcp(addr));
+    }
+
+    #[test]
+    fn link_local_addresses_keep_their_zone_id() {
+        let Ok(DnsAddress::Udp(SocketAddr::V6(addr))) =
+            DnsAddress::try_from("udp://[fe80::1%2]:53")
+        else {
+            unreachable!()
+        };
+        assert_eq!(addr.ip().to_string(), "fe80::1");
+        assert_eq!(addr.port(), 53);
+        assert_eq!(addr.scope_id(), 2);
+
+        assert!(DnsAddress::try_from("udp://[fe80::1%eth0]:53").is_err());
+        assert_eq!(
+            DnsAddress::try_from("tcp://[2001:db8::1]:53"),
+            Ok(DnsAddress::Tcp("[2001:db8::1]:53".parse().unwrap()))
+        );
+    }
+
+    #[tokio::test]
+    async fn dns_class_is_sent_in_updates() {
+        let (addr, received) = update_server().await;
+        let provider = provider(addr).with_dns_class(DNSClass::CH);
+
+        provider.delete("version.bind", "bind").await.unwrap();
+        provider
+            .create(
+                "version.bind",
+                DnsRecord::TXT {
+                    content: "9.18".to_string(),
+                },
+                0,
+                "bind",
+            )
+            .await
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        let (_, delete) = &received[0];
+        assert_eq!(delete.queries()[0].query_class(), DNSClass::CH);
+        let (_, create) = &received[1];
+        assert_eq!(create.queries()[0].query_class(), DNSClass::CH);
+        assert_eq!(create.updates()[0].dns_class(), DNSClass::CH);
+    }
+
+    #[test]
+    fn qualified_targets_are_anchored_at_the_root() {
+        let (_, rdata) = convert_record(DnsRecord::MX {
+            content: "mail.other-domain.com".to_string(),
+            priority: 10,
+        })
+        .unwrap();
+        let RData::MX(mx) = rdata else { unreachable!() };
+        assert!(mx.exchange().is_fqdn());
+        assert_eq!(mx.exchange().to_ascii(), "mail.other-domain.com.");
+
+        let (_, rdata) = convert_record(DnsRecord::CNAME {
+            content: "www".to_string(),
+        })
+        .unwrap();
+        let RData::CNAME(cname) = rdata else {
+            unreachable!()
+        };
+        assert!(!cname.0.is_fqdn());
+    }
+
+    #[test]
+    fn base64_tsig_keys_are_decoded() {
+        let updater = DnsUpdater::new_rfc2136_tsig_base64(
+            "udp://192.0.2.53:53",
+            "key",
+            " c2VjcmV0\n",
+            TsigAlgorithm::HmacSha256,
+        )
+        .unwrap();
+        let DnsUpdater::Rfc2136(provider) = updater else {
+            unreachable!()
+        };
+        let Signer::TSIG(signer) = provider.signer.as_ref() else {
+            unreachable!()
+        };
+        assert_eq!(signer.key(), b"secret");
+
+        assert!(matches!(
+            DnsUpdater::new_rfc2136_tsig_base64(
+                "udp://192.0.2.53:53",
+                "key",
+                "not base64!",
+                TsigAlgorithm::HmacSha256,
+            ),
+            Err(Error::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,411 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::{future::Future, net::IpAddr, time::Duration};
+
+use hickory_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    error::{ResolveError, ResolveErrorKind},
+    proto::rr::{RData, RecordType},
+    TokioAsyncResolver,
+};
+
+use crate::{providers::rfc2136::convert_rdata, DnsRecord, Error, IntoFqdn};
+
+const MAX_CNAME_HOPS: usize = 16;
+
+/// Where and how often [`wait_for_propagation`] looks for a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropagationConfig {
+    /// Nameservers to query, e.g. a specific anycast PoP. When empty, the
+    /// authoritative nameservers of the record's zone are used.
+    pub nameservers: Vec<IpAddr>,
+    pub attempts: u32,
+    pub interval: Duration,
+}
+
+impl Default for PropagationConfig {
+    fn default() -> Self {
+        Self {
+            nameservers: Vec::new(),
+            attempts: 30,
+            interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Follow the CNAME chain starting at `name` and return the final target.
+/// If `name` is not an alias it is returned unchanged.
+pub async fn follow_cname(name: impl IntoFqdn<'_>) -> crate::Result<String> {
+    let resolver = system_resolver()?;
+    let mut name = name.into_fqdn().into_owned();
+
+    for _ in 0..MAX_CNAME_HOPS {
+        match resolver.lookup(name.as_str(), RecordType::CNAME).await {
+            Ok(lookup) => {
+                match lookup.record_iter().find_map(|record| match record.data() {
+                    Some(RData::CNAME(target)) => Some(target.to_utf8()),
+                    _ => None,
+                }) {
+                    Some(target) => name = target,
+                    None => return Ok(name),
+                }
+            }
+            Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                return Ok(name)
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Err(Error::Api(format!(
+        "CNAME chain for {name} exceeds {MAX_CNAME_HOPS} hops"
+    )))
+}
+
+/// Find the zone `name` belongs to, using the SOA record returned by the
+/// authoritative servers.
+pub async fn resolve_zone(name: impl IntoFqdn<'_>) -> crate::Result<String> {
+    let resolver = system_resolver()?;
+    let name = name.into_fqdn();
+
+    match resolver.lookup(name.as_ref(), RecordType::SOA).await {
+        Ok(lookup) => lookup
+            .record_iter()
+            .find(|record| record.record_type() == RecordType::SOA)
+            .map(|record| record.name().to_utf8()),
+        Err(err) => match err.kind() {
+            ResolveErrorKind::NoRecordsFound { soa: Some(soa), .. } => Some(soa.name().to_utf8()),
+            _ => return Err(err.into()),
+        },
+    }
+    .ok_or_else(|| Error::ZoneNotFound(name.as_ref().to_string()))
+}
+
+/// Look up the host names of the nameservers in the NS set of `zone`.
+pub async fn zone_nameservers(zone: impl IntoFqdn<'_>) -> crate::Result<Vec<String>> {
+    Ok(system_resolver()?
+        .lookup(zone.into_fqdn().as_ref(), RecordType::NS)
+        .await?
+        .record_iter()
+        .filter_map(|record| match record.data() {
+            Some(RData::NS(ns)) => Some(ns.to_utf8().trim_end_matches('.').to_string()),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Resolve the addresses of the authoritative nameservers of the zone `name`
+/// belongs to.
+pub async fn authoritative_nameservers(name: impl IntoFqdn<'_>) -> crate::Result<Vec<IpAddr>> {
+    let resolver = system_resolver()?;
+    let zone = resolve_zone(name).await?;
+    let mut addresses = Vec::new();
+
+    for ns in zone_nameservers(zone.as_str()).await? {
+        addresses.extend(resolver.lookup_ip(format!("{ns}.")).await?.iter());
+    }
+
+    if addresses.is_empty() {
+        Err(Error::Api(format!("No nameservers found for zone {zone}")))
+    } else {
+        Ok(addresses)
+    }
+}
+
+/// Whether every nameserver in `nameservers` already answers `name` with a
+/// record matching `expected`.
+pub async fn is_propagated(
+    nameservers: &[IpAddr],
+    name: impl IntoFqdn<'_>,
+    expected: &DnsRecord,
+) -> crate::Result<bool> {
+    let name = name.into_fqdn();
+    let record_type = RecordType::from(expected.as_type());
+
+    for nameserver in nameservers {
+        let found = match nameserver_resolver(*nameserver)
+            .lookup(name.as_ref(), record_type)
+            .await
+        {
+            Ok(lookup) => lookup
+                .record_iter()
+                .filter_map(|record| record.data().and_then(convert_rdata))
+                .any(|record| record.content_eq(expected)),
+            Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => false,
+            Err(err) => return Err(err.into()),
+        };
+        if !found {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Wait until `name` has a record matching `expected` on the nameservers
+/// given in `config`, checking `config.attempts` times before failing with
+/// [`Error::Timeout`].
+pub async fn wait_for_propagation(
+    name: impl IntoFqdn<'_>,
+    expected: &DnsRecord,
+    config: &PropagationConfig,
+) -> crate::Result<()> {
+    let name = name.into_fqdn();
+    let nameservers = if config.nameservers.is_empty() {
+        authoritative_nameservers(name.as_ref()).await?
+    } else {
+        config.nameservers.clone()
+    };
+
+    for attempt in 0..config.attempts {
+        if attempt > 0 {
+            tokio::time::sleep(config.interval).await;
+        }
+        if is_propagated(&nameservers, name.as_ref(), expected).await? {
+            return Ok(());
+        }
+    }
+
+    Err(Error::Timeout(format!(
+        "{} did not propagate after {} attempts",
+        name.as_ref(),
+        config.attempts
+    )))
+}
+
+/// Same as [`wait_for_propagation`], but stops with [`Error::Cancelled`] as
+/// soon as `cancel` completes (e.g. a `CancellationToken::cancelled()` future).
+pub async fn wait_for_propagation_cancellable(
+    name: impl IntoFqdn<'_>,
+    expected: &DnsRecord,
+    config: &PropagationConfig,
+    cancel: impl Future<Output = ()>,
+) -> crate::Result<()> {
+    tokio::select! {
+        biased;
+        _ = cancel => Err(Error::Cancelled),
+        result = wait_for_propagation(name, expected, config) => result,
+    }
+}
+
+fn nameserver_resolver(nameserver: IpAddr) -> TokioAsyncResolver {
+    #[cfg_attr(not(test), allow(unused_mut))]
+    let mut port = 53;
+    #[cfg(test)]
+    if let Some(addr) = TEST_NAMESERVER.get() {
+        port = addr.port();
+    }
+
+    let mut opts = ResolverOpts::default();
+    opts.cache_size = 0;
+    TokioAsyncResolver::tokio(
+        ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[nameserver], port, true),
+        ),
+        opts,
+    )
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Sends lookups made through [`system_resolver`] to a mock server, and
+    /// queries to specific nameservers to its port.
+    pub(crate) static TEST_NAMESERVER: std::cell::Cell<Option<std::net::SocketAddr>> =
+        const { std::cell::Cell::new(None) };
+}
+
+fn system_resolver() -> crate::Result<TokioAsyncResolver> {
+    #[cfg(test)]
+    if let Some(addr) = TEST_NAMESERVER.get() {
+        return Ok(TokioAsyncResolver::tokio(
+            ResolverConfig::from_parts(
+                None,
+                vec![],
+                NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true),
+            ),
+            ResolverOpts::default(),
+        ));
+    }
+
+    TokioAsyncResolver::tokio_from_system_conf().map_err(Into::into)
+}
+
+impl From<ResolveError> for Error {
+    fn from(e: ResolveError) -> Self {
+        Error::Client(e.to_string())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        str::FromStr,
+        time::{Duration, Instant},
+    };
+
+    use hickory_resolver::proto::{
+        op::{Message, MessageType},
+        rr::{
+            rdata::{CNAME, SOA},
+            Name, RData, Record, RecordType,
+        },
+        serialize::binary::BinEncodable,
+    };
+    use tokio::net::UdpSocket;
+
+    use super::{
+        follow_cname, resolve_zone, wait_for_propagation, wait_for_propagation_cancellable,
+        PropagationConfig, TEST_NAMESERVER,
+    };
+    use crate::{DnsRecord, Error};
+
+    /// Start a DNS server answering CNAME queries from `aliases`, and every
+    /// other query with an empty answer carrying the SOA of the zone the
+    /// name belongs to, out of `zones`. Lookups made through the system
+    /// resolver on this thread are sent to it.
+    pub(crate) async fn mock_dns(
+        aliases: &[(&'static str, &'static str)],
+        zones: &[&'static str],
+    ) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let aliases = aliases.to_vec();
+        let zones = zones.to_vec();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Ok(request) = Message::from_vec(&buf[..len]) else {
+                    continue;
+                };
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_recursion_desired(true)
+                    .set_recursion_available(true)
+                    .add_queries(request.queries().to_vec());
+
+                for query in request.queries() {
+                    let name = query.name().to_ascii();
+                    let alias = aliases
+                        .iter()
+                        .find(|(alias, _)| name.trim_end_matches('.') == *alias);
+                    match alias {
+                        Some((_, target)) if query.query_type() == RecordType::CNAME => {
+                            response.add_answer(Record::from_rdata(
+                                query.name().clone(),
+                                300,
+                                RData::CNAME(CNAME(Name::from_str(target).unwrap())),
+                            ));
+                        }
+                        _ => {
+                            if let Some(zone) = zones
+                                .iter()
+                                .find(|zone| name.trim_end_matches('.').ends_with(*zone))
+                            {
+                                let zone = Name::from_str(&format!("{zone}.")).unwrap();
+                                response.add_name_server(Record::from_rdata(
+                                    zone.clone(),
+                                    300,
+                                    RData::SOA(SOA::new(
+                                        zone.clone(),
+                                        zone,
+                                        1,
+                                        3600,
+                                        600,
+                                        86400,
+                                        300,
+                                    )),
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                let _ = socket.send_to(&response.to_bytes().unwrap(), peer).await;
+            }
+        });
+
+        TEST_NAMESERVER.set(Some(addr));
+        addr
+    }
+
+    fn config(attempts: u32) -> PropagationConfig {
+        PropagationConfig {
+            nameservers: vec![Ipv4Addr::LOCALHOST.into()],
+            attempts,
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    fn record() -> DnsRecord {
+        DnsRecord::A {
+            content: Ipv4Addr::new(192, 0, 2, 1),
+        }
+    }
+
+    #[tokio::test]
+    async fn propagation_wait_reports_timeouts() {
+        let result = wait_for_propagation("www.example.com", &record(), &config(0)).await;
+
+        assert!(matches!(result, Err(Error::Timeout(_))), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn propagation_wait_can_be_cancelled() {
+        // A nameserver that never answers, so the wait can only end by being
+        // cancelled.
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        TEST_NAMESERVER.set(Some(socket.local_addr().unwrap()));
+
+        let started = Instant::now();
+        let result = wait_for_propagation_cancellable(
+            "www.example.com",
+            &record(),
+            &config(30),
+            tokio::time::sleep(Duration::from_millis(50)),
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Cancelled)), "{result:?}");
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn cname_chains_are_followed() {
+        mock_dns(
+            &[
+                ("_acme-challenge.example.com", "_acme-challenge.example.net"),
+                ("_acme-challenge.example.net", "acme.example.org"),
+            ],
+            &["example.com", "example.org"],
+        )
+        .await;
+
+        assert_eq!(
+            follow_cname("_acme-challenge.example.com").await.unwrap(),
+            "acme.example.org."
+        );
+        assert_eq!(
+            follow_cname("www.example.com").await.unwrap(),
+            "www.example.com."
+        );
+        assert_eq!(
+            resolve_zone("acme.example.org").await.unwrap(),
+            "example.org."
+        );
+    }
+}
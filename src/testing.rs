@@ -0,0 +1,92 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::{net::IpAddr, time::Duration};
+
+use crate::{resolver::is_propagated, DnsRecord, Error, IntoFqdn};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Query `nameservers` directly until `name` has a record matching `expected`,
+/// or fail once `timeout` has elapsed. Meant for integration tests that need
+/// to wait for a change to reach the zone's authoritative servers. Lookup
+/// errors other than a missing record are returned right away.
+pub async fn poll_until_propagated(
+    nameservers: &[IpAddr],
+    name: impl IntoFqdn<'_>,
+    expected: &DnsRecord,
+    timeout: Duration,
+) -> crate::Result<()> {
+    if nameservers.is_empty() {
+        return Err(Error::Config("No nameservers to poll".to_string()));
+    }
+    let name = name.into_fqdn();
+
+    tokio::time::timeout(timeout, async {
+        while !is_propagated(nameservers, name.as_ref(), expected).await? {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|_| {
+        Error::Timeout(format!(
+            "{} did not propagate within {timeout:?}",
+            name.as_ref()
+        ))
+    })?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::Ipv4Addr,
+        time::{Duration, Instant},
+    };
+
+    use tokio::net::UdpSocket;
+
+    use super::poll_until_propagated;
+    use crate::{resolver::TEST_NAMESERVER, DnsRecord, Error};
+
+    fn record() -> DnsRecord {
+        DnsRecord::A {
+            content: Ipv4Addr::new(192, 0, 2, 1),
+        }
+    }
+
+    #[tokio::test]
+    async fn polling_times_out() {
+        // A nameserver that never answers.
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        TEST_NAMESERVER.set(Some(socket.local_addr().unwrap()));
+
+        let started = Instant::now();
+        let result = poll_until_propagated(
+            &[Ipv4Addr::LOCALHOST.into()],
+            "www.example.com",
+            &record(),
+            Duration::from_millis(100),
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Timeout(_))), "{result:?}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn polling_without_nameservers_is_rejected() {
+        let result =
+            poll_until_propagated(&[], "www.example.com", &record(), Duration::from_secs(1)).await;
+
+        assert!(matches!(result, Err(Error::Config(_))), "{result:?}");
+    }
+}